@@ -36,18 +36,46 @@
 //! ```bash
 //! memtrace-ui -o <your_trace_file>
 //! ```
+//!
+//! Open a folded stack file produced by other tools (inferno-collapse, perf, ...)
+//! ```bash
+//! memtrace-ui -o <your_stacks.folded>
+//! ```
+//!
+//! Find sources of a trace recorded on another machine (std sources are read from the `rust-src` component, dependencies from the local cargo registry)
+//! ```bash
+//! memtrace-ui -o <your_trace_file> --remap-path-prefix /build/ci/workspace=~/src/project --source-dir ~/src
+//! ```
+//!
+//! Open source locations in your editor from the context menus, `$VISUAL` or `$EDITOR` are used when started from a terminal
+//! ```bash
+//! memtrace-ui -o <your_trace_file> --editor 'code -g {file}:{line}'
+//! ```
+//!
+//! Hide known intentional leaks from the Leaks tab, one `fn:<regex>` or `stack:<regex>` pattern per line, or `fn-glob:<glob>` and `stack-glob:<glob>` with `*` and `?` wildcards
+//! ```bash
+//! memtrace-ui -o <your_trace_file> --suppressions leaks.supp
+//! ```
+//!
+//! Export a flamegraph as a folded stack file (peak, leaked, allocations or temporary), with inlined functions suffixed by `_[i]` like inferno does
+//! ```bash
+//! memtrace-ui -o <your_trace_file> --export-folded peak <output.folded>
+//! ```
 
 mod prelude;
 mod ui;
 
-use crate::ui::MemInfo;
+use crate::ui::{
+    EditorCommand, FoldedFile, MemInfo, MemoryKind, Settings, SourceResolver, Suppressions,
+};
 use anyhow::{Context, anyhow};
 use clap::Parser;
 use memtrace_utils::common::download_lib_if_needed;
 use memtrace_utils::interpret::Interpreter;
+use memtrace_utils::parser::AccumulatedData;
 use prelude::*;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const LIB_VERSION: &str = "v0.5.0";
 
@@ -60,6 +88,38 @@ struct Opt {
         help = "Open an existing trace file"
     )]
     open: bool,
+    #[clap(
+        long,
+        num_args = 2,
+        value_names = ["KIND", "PATH"],
+        help = "Export the flamegraph of KIND (peak, leaked, allocations, temporary) as a folded stack file and exit"
+    )]
+    export_folded: Option<Vec<String>>,
+    #[clap(
+        long,
+        value_name = "FROM=TO",
+        value_parser = parse_remap,
+        help = "Replace the FROM prefix of source paths recorded in the trace with TO"
+    )]
+    remap_path_prefix: Vec<(String, String)>,
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Search source files recorded in the trace in DIR, matching at least their parent directory and file name"
+    )]
+    source_dir: Vec<PathBuf>,
+    #[clap(
+        long,
+        value_name = "TEMPLATE",
+        help = "Command opening a source location, e.g. 'code -g {file}:{line}' [default: $VISUAL or $EDITOR when started from a terminal]"
+    )]
+    editor: Option<String>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Hide the leaks matching the fn:<regex>, stack:<regex>, fn-glob:<glob> or stack-glob:<glob> lines of FILE in the Leaks tab"
+    )]
+    suppressions: Option<PathBuf>,
     target: String,
     args: Vec<String>,
 }
@@ -67,6 +127,38 @@ struct Opt {
 fn main() -> Result<()> {
     let opt = Opt::parse();
 
+    let settings = Settings {
+        source_resolver: SourceResolver {
+            remaps: opt.remap_path_prefix,
+            search_dirs: opt.source_dir,
+        },
+        editor: opt
+            .editor
+            .map(EditorCommand::new)
+            .or_else(EditorCommand::from_env),
+        suppressions: opt
+            .suppressions
+            .map(Suppressions::read)
+            .transpose()
+            .context("failed to read suppressions")?
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    if opt.open && is_folded_file(&opt.target) {
+        if opt.export_folded.is_some() {
+            anyhow::bail!("--export-folded requires a trace file");
+        }
+
+        let folded = FoldedFile::read(&opt.target).context("failed to read folded file")?;
+
+        let info = MemInfo::new(opt.target, AccumulatedData::new(), None);
+
+        ui::run_ui(info, Some(folded), settings).map_err(|e| anyhow!("{:?}", e))?;
+
+        return Ok(());
+    }
+
     let Some(home) = env::var_os("HOME") else {
         anyhow::bail!("missing $HOME");
     };
@@ -99,12 +191,26 @@ fn main() -> Result<()> {
         .parse_file(&trace_filepath)
         .context("failed to parse trace file")?;
 
-    let info = MemInfo {
-        app_name: opt.target,
-        data,
-    };
+    let info = MemInfo::new(opt.target, data, Some(trace_filepath.into()));
 
-    ui::run_ui(info).map_err(|e| anyhow!("{:?}", e))?;
+    if let Some([kind, path]) = opt.export_folded.as_deref() {
+        let kind = kind.parse::<MemoryKind>().map_err(|e| anyhow!(e))?;
+        let lines = ui::FlamegraphPage::make_frame_lines(&info, kind);
+        ui::write_folded(&lines, path).context("failed to write folded file")?;
+        return Ok(());
+    }
+
+    ui::run_ui(info, None, settings).map_err(|e| anyhow!("{:?}", e))?;
 
     Ok(())
 }
+
+fn parse_remap(remap: &str) -> Result<(String, String)> {
+    SourceResolver::parse_remap(remap).ok_or_else(|| anyhow!("expected FROM=TO, got '{remap}'"))
+}
+
+fn is_folded_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext == "folded" || ext == "collapsed")
+}
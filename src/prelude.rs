@@ -1 +1 @@
-pub type Result<T = ()> = anyhow::Result<T>;
\ No newline at end of file
+pub type Result<T = ()> = anyhow::Result<T>;
@@ -0,0 +1,413 @@
+use crate::ui::stack::{StackRef, frame_location, ip_inline_frames};
+use memtrace_utils::parser::{AccumulatedData, Allocation, Frame};
+use std::collections::HashMap;
+use std::num::NonZero;
+use std::thread;
+
+pub type NodeId = u32;
+
+pub const ROOT: NodeId = 0;
+
+/// Allocations handled by one thread while building the tree.
+const MIN_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Costs of allocations, summed over a node of the call tree or a line of source code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cost {
+    pub peaked: u64,
+    pub leaked: u64,
+    pub allocations: u64,
+    pub temporary: u64,
+}
+
+impl Cost {
+    pub fn of(allocation: &Allocation) -> Self {
+        Self {
+            peaked: allocation.data.peak,
+            leaked: allocation.data.leaked,
+            allocations: allocation.data.allocations,
+            temporary: allocation.data.temporary,
+        }
+    }
+
+    pub fn add(&mut self, other: &Cost) {
+        self.peaked += other.peaked;
+        self.leaked += other.leaked;
+        self.allocations += other.allocations;
+        self.temporary += other.temporary;
+    }
+}
+
+/// A function called from a source location of its caller. Indices point into
+/// `AccumulatedData::strings` and are zero when unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CallSite {
+    pub fn_idx: usize,
+    pub file_idx: usize,
+    pub line_number: u32,
+}
+
+impl CallSite {
+    pub fn name<'a>(&self, data: &'a AccumulatedData) -> &'a str {
+        string(data, self.fn_idx)
+    }
+
+    pub fn file_name<'a>(&self, data: &'a AccumulatedData) -> &'a str {
+        string(data, self.file_idx)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub site: CallSite,
+    pub parent: NodeId,
+    /// Number of frames of `trace_idx` down to the node, which differs from the depth in the
+    /// tree when recursion is collapsed.
+    pub depth: u32,
+    pub children: Vec<NodeId>,
+    /// Costs of all allocations made by the node and its callees.
+    pub cost: Cost,
+    /// Costs of the allocations made by the node itself.
+    pub self_cost: Cost,
+    /// Lowest index of the traces passing through the node, whose stack stands for the node.
+    pub trace_idx: u64,
+    /// The function was inlined into its caller.
+    pub inlined: bool,
+}
+
+/// Call tree of all allocations, stored as a flat arena in which parents precede their children.
+pub struct CallTree {
+    nodes: Vec<Node>,
+    collapse_recursion: bool,
+    /// Number of levels below the root.
+    max_depth: u32,
+}
+
+impl CallTree {
+    /// Builds the tree on all cores, each thread merging a chunk of the allocations into its
+    /// own tree before the partial trees are merged.
+    ///
+    /// With `collapse_recursion` a function already on the path returns to its earlier node
+    /// instead of nesting again, so recursion appears once with the aggregated cost.
+    pub fn new(data: &AccumulatedData, collapse_recursion: bool) -> Self {
+        let threads = thread::available_parallelism().map_or(1, NonZero::get);
+        let chunk_size = data.allocations.len().div_ceil(threads).max(MIN_CHUNK_SIZE);
+
+        let mut builders = thread::scope(|scope| {
+            let handles = data
+                .allocations
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut builder = Builder::new(data, collapse_recursion);
+                        for allocation in chunk {
+                            builder.add(allocation);
+                        }
+                        builder
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("call tree builder panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        let mut tree = match builders.pop() {
+            Some(builder) => builder,
+            None => Builder::new(data, collapse_recursion),
+        };
+        for builder in builders {
+            tree.merge(builder);
+        }
+
+        tree.finish()
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id as usize]
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    pub fn name<'a>(&self, data: &'a AccumulatedData, id: NodeId) -> &'a str {
+        match id {
+            ROOT => "all",
+            _ => self.node(id).site.name(data),
+        }
+    }
+
+    /// Ancestors of a node from the root down to its parent.
+    pub fn ancestors(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ancestors = Vec::new();
+        let mut id = id;
+        while id != ROOT {
+            id = self.node(id).parent;
+            ancestors.push(id);
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
+    /// Call stack from the outermost caller down to the node.
+    pub fn stack(&self, id: NodeId) -> StackRef {
+        let node = self.node(id);
+        StackRef {
+            trace_idx: node.trace_idx,
+            depth: node.depth as usize,
+        }
+    }
+
+    /// Finds the node of a call stack, stopping at the deepest node found when the tree does
+    /// not contain the whole stack.
+    pub fn find(&self, frames: &[&Frame]) -> NodeId {
+        let mut id = ROOT;
+        let mut caller = (0, 0);
+
+        for frame in frames {
+            let (fn_idx, file_idx, line_number) = frame_location(frame);
+            if self.collapse_recursion
+                && let Some(ancestor) = recursive_ancestor(&self.nodes, id, fn_idx)
+            {
+                id = ancestor;
+                caller = (file_idx, line_number);
+                continue;
+            }
+
+            let site = CallSite {
+                fn_idx,
+                file_idx: caller.0,
+                line_number: caller.1,
+            };
+
+            let child = self
+                .node(id)
+                .children
+                .iter()
+                .find(|child| self.node(**child).site == site);
+            let Some(child) = child else {
+                break;
+            };
+
+            id = *child;
+            caller = (file_idx, line_number);
+        }
+
+        id
+    }
+}
+
+/// Innermost node of a trace and the source location of its frame, which is the call site of
+/// the children.
+#[derive(Clone, Copy)]
+struct TraceEnd {
+    node: NodeId,
+    file_idx: usize,
+    line_number: u32,
+    frames: u32,
+}
+
+struct Builder<'a> {
+    data: &'a AccumulatedData,
+    collapse_recursion: bool,
+    nodes: Vec<Node>,
+    index: HashMap<(NodeId, CallSite), NodeId>,
+    trace_ends: HashMap<u64, TraceEnd>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(data: &'a AccumulatedData, collapse_recursion: bool) -> Self {
+        let root = Node {
+            site: CallSite::default(),
+            parent: ROOT,
+            depth: 0,
+            children: Vec::new(),
+            cost: Cost::default(),
+            self_cost: Cost::default(),
+            trace_idx: 0,
+            inlined: false,
+        };
+
+        Self {
+            data,
+            collapse_recursion,
+            nodes: vec![root],
+            index: HashMap::new(),
+            trace_ends: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, allocation: &Allocation) {
+        let id = self.trace_end(allocation.trace_idx).node;
+        self.nodes[id as usize].self_cost.add(&Cost::of(allocation));
+    }
+
+    fn child(
+        &mut self,
+        parent: NodeId,
+        site: CallSite,
+        trace_idx: u64,
+        depth: u32,
+        inlined: bool,
+    ) -> NodeId {
+        let nodes = &mut self.nodes;
+
+        let id = *self.index.entry((parent, site)).or_insert_with(|| {
+            let id = nodes.len() as NodeId;
+            nodes.push(Node {
+                site,
+                parent,
+                depth,
+                children: Vec::new(),
+                cost: Cost::default(),
+                self_cost: Cost::default(),
+                trace_idx,
+                inlined,
+            });
+            id
+        });
+
+        // keep the same trace whatever the order the traces were inserted in
+        let node = &mut nodes[id as usize];
+        if trace_idx < node.trace_idx {
+            node.trace_idx = trace_idx;
+            node.depth = depth;
+        }
+        id
+    }
+
+    /// Inserts the frames of a trace, reusing the nodes of the traces already inserted as most
+    /// traces share their callers.
+    fn trace_end(&mut self, trace_idx: u64) -> TraceEnd {
+        let mut pending = Vec::new();
+        let mut idx = trace_idx;
+        let mut end = loop {
+            if idx == 0 {
+                break TraceEnd {
+                    node: ROOT,
+                    file_idx: 0,
+                    line_number: 0,
+                    frames: 0,
+                };
+            }
+            if let Some(end) = self.trace_ends.get(&idx) {
+                break *end;
+            }
+            pending.push(idx);
+            idx = self.data.traces[idx as usize - 1].parent_idx;
+        };
+
+        let data = self.data;
+        for idx in pending.into_iter().rev() {
+            let trace = &data.traces[idx as usize - 1];
+            let ip_info = &data.instruction_pointers[trace.ip_idx as usize - 1];
+
+            for (frame, inlined) in ip_inline_frames(ip_info).rev() {
+                let (fn_idx, file_idx, line_number) = frame_location(frame);
+                let frames = end.frames + 1;
+
+                let recursive = self
+                    .collapse_recursion
+                    .then(|| recursive_ancestor(&self.nodes, end.node, fn_idx))
+                    .flatten();
+                let node = match recursive {
+                    Some(ancestor) => ancestor,
+                    None => {
+                        let site = CallSite {
+                            fn_idx,
+                            file_idx: end.file_idx,
+                            line_number: end.line_number,
+                        };
+                        self.child(end.node, site, idx, frames, inlined)
+                    }
+                };
+
+                end = TraceEnd {
+                    node,
+                    file_idx,
+                    line_number,
+                    frames,
+                };
+            }
+
+            self.trace_ends.insert(idx, end);
+        }
+
+        end
+    }
+
+    fn merge(&mut self, other: Builder) {
+        let mut ids = vec![ROOT];
+        self.nodes[ROOT as usize]
+            .self_cost
+            .add(&other.nodes[ROOT as usize].self_cost);
+
+        for node in &other.nodes[1..] {
+            let id = self.child(
+                ids[node.parent as usize],
+                node.site,
+                node.trace_idx,
+                node.depth,
+                node.inlined,
+            );
+            self.nodes[id as usize].self_cost.add(&node.self_cost);
+            ids.push(id);
+        }
+    }
+
+    fn finish(self) -> CallTree {
+        let mut nodes = self.nodes;
+
+        for id in (0..nodes.len()).rev() {
+            let self_cost = nodes[id].self_cost;
+            nodes[id].cost.add(&self_cost);
+            if id != ROOT as usize {
+                let cost = nodes[id].cost;
+                let parent = nodes[id].parent as usize;
+                nodes[parent].cost.add(&cost);
+                nodes[parent].children.push(id as NodeId);
+            }
+        }
+
+        let sites = nodes.iter().map(|node| node.site).collect::<Vec<_>>();
+        for node in &mut nodes {
+            node.children.sort_by_key(|child| sites[*child as usize]);
+        }
+
+        // parents precede their children
+        let mut levels = vec![0u32; nodes.len()];
+        for id in 1..nodes.len() {
+            levels[id] = levels[nodes[id].parent as usize] + 1;
+        }
+
+        CallTree {
+            nodes,
+            collapse_recursion: self.collapse_recursion,
+            max_depth: levels.into_iter().max().unwrap_or(0),
+        }
+    }
+}
+
+/// The node itself or its nearest ancestor calling `fn_idx`.
+fn recursive_ancestor(nodes: &[Node], id: NodeId, fn_idx: usize) -> Option<NodeId> {
+    let mut id = id;
+    while id != ROOT {
+        let node = &nodes[id as usize];
+        if node.site.fn_idx == fn_idx {
+            return Some(id);
+        }
+        id = node.parent;
+    }
+    None
+}
+
+pub fn string(data: &AccumulatedData, idx: usize) -> &str {
+    if idx > 0 && idx <= data.strings.len() {
+        &data.strings[idx - 1]
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,221 @@
+use crate::ui::calltree::Cost;
+use crate::ui::source_cache::{Source, SourceCache};
+use crate::ui::sources::SourceResolver;
+use bytesize::ByteSize;
+use egui::*;
+use egui_extras::syntax_highlighting::{CodeTheme, highlight};
+use egui_extras::{Column, TableBuilder};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const ROW_HEIGHT: f32 = 15.0;
+const HEAT_BAR_WIDTH: f32 = 40.0;
+
+/// Line costs of every source file, keyed by file name and line number.
+pub type FileAnnotations = HashMap<String, BTreeMap<u32, Cost>>;
+
+pub struct CodeLoader {
+    sources: SourceCache,
+    scrolled_to: Option<(String, u32)>,
+    new_search_dir: String,
+    new_remap: (String, String),
+}
+
+impl CodeLoader {
+    pub fn new() -> Self {
+        Self {
+            sources: SourceCache::new(),
+            scrolled_to: None,
+            new_search_dir: String::new(),
+            new_remap: (String::new(), String::new()),
+        }
+    }
+
+    /// Shows the whole file with the costs of its lines in a gutter, scrolling to
+    /// `line_number` whenever the location changes.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        resolver: &mut SourceResolver,
+        file_name: &str,
+        line_number: u32,
+        annotations: Option<&BTreeMap<u32, Cost>>,
+    ) {
+        if file_name.is_empty() {
+            return;
+        }
+
+        let code = match self.sources.get(ui.ctx(), resolver, file_name) {
+            Source::Loaded(code) => code,
+            Source::Loading => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Loading {file_name}…"));
+                });
+                return;
+            }
+            Source::NotFound => {
+                if self.show_not_found(ui, resolver, file_name) {
+                    self.sources.forget_missing();
+                }
+                return;
+            }
+        };
+
+        let lines = code.lines().collect::<Vec<_>>();
+
+        let location = (file_name.to_string(), line_number);
+        let scroll_to = self.scrolled_to.as_ref() != Some(&location);
+        self.scrolled_to = Some(location);
+
+        let max_peak = annotations
+            .and_then(|lines| lines.values().map(|stats| stats.peaked).max())
+            .unwrap_or_default();
+
+        let language = language_of(file_name);
+        let theme = CodeTheme::from_style(ui.style());
+
+        ScrollArea::horizontal().show(ui, |ui| {
+            let mut table = TableBuilder::new(ui)
+                .id_salt(file_name)
+                .resizable(false)
+                .striped(false)
+                .auto_shrink(false)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .column(Column::auto().at_least(30.0))
+                .column(Column::auto().at_least(HEAT_BAR_WIDTH + 150.0))
+                .column(Column::auto().clip(false));
+
+            if scroll_to && line_number > 0 {
+                table = table.scroll_to_row(line_number as usize - 1, Some(Align::Center));
+            }
+
+            table.body(|body| {
+                body.rows(ROW_HEIGHT, lines.len(), |mut row| {
+                    let index = row.index();
+                    let number = (index + 1) as u32;
+                    let stats = annotations.and_then(|lines| lines.get(&number));
+
+                    row.set_selected(number == line_number);
+
+                    row.col(|ui| {
+                        ui.label(RichText::new(format!("{}", number)).monospace().weak());
+                    });
+
+                    row.col(|ui| {
+                        let Some(stats) = stats else {
+                            return;
+                        };
+
+                        let (rect, _) = ui.allocate_exact_size(
+                            vec2(HEAT_BAR_WIDTH, ROW_HEIGHT - 4.0),
+                            Sense::hover(),
+                        );
+                        let share = if max_peak > 0 {
+                            stats.peaked as f32 / max_peak as f32
+                        } else {
+                            0.0
+                        };
+                        let mut bar = rect;
+                        bar.set_width(rect.width() * share);
+                        ui.painter()
+                            .rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+                        ui.painter()
+                            .rect_filled(bar, 0.0, Color32::from_rgb(230, 120, 60));
+
+                        let mut text = format!(
+                            "{} / {}",
+                            ByteSize::b(stats.peaked),
+                            ByteSize::b(stats.leaked)
+                        );
+                        if stats.allocations > 1 {
+                            text.push_str(&format!(" (x{})", stats.allocations));
+                        }
+                        ui.label(RichText::new(text).small())
+                            .on_hover_text("Peak / Leaked (allocations)");
+                    });
+
+                    row.col(|ui| {
+                        let line = lines[index];
+                        // the highlighter knows neither block comments nor string escapes, a
+                        // line on its own cannot leave it in the wrong state for the next ones
+                        let job = match language {
+                            Some(language) => {
+                                highlight(ui.ctx(), ui.style(), &theme, line, language)
+                            }
+                            None => text::LayoutJob::simple_singleline(
+                                line.to_string(),
+                                TextStyle::Monospace.resolve(ui.style()),
+                                ui.visuals().text_color(),
+                            ),
+                        };
+                        ui.add(Label::new(job).wrap_mode(TextWrapMode::Extend));
+                    });
+                });
+            });
+        });
+    }
+}
+
+impl CodeLoader {
+    /// Returns whether the resolver changed.
+    fn show_not_found(
+        &mut self,
+        ui: &mut Ui,
+        resolver: &mut SourceResolver,
+        file_name: &str,
+    ) -> bool {
+        let mut changed = false;
+
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("⚠ Source not found: {file_name}"),
+        );
+        ui.label("The trace may have been recorded on another machine. Choose a directory to search in, or remap the path prefix.");
+        if file_name.starts_with("/rustc/") {
+            ui.label("Sources of the standard library are read from the rust-src component, install it with `rustup component add rust-src`.");
+        }
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Choose directory…");
+            ui.text_edit_singleline(&mut self.new_search_dir);
+            if ui.button("Add search directory").clicked() && !self.new_search_dir.is_empty() {
+                let dir = std::mem::take(&mut self.new_search_dir);
+                resolver.search_dirs.push(dir.into());
+                changed = true;
+            }
+        });
+
+        if self.new_remap.0.is_empty()
+            && let Some(parent) = Path::new(file_name).parent()
+        {
+            self.new_remap.0 = parent.to_string_lossy().to_string();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Remap prefix");
+            ui.text_edit_singleline(&mut self.new_remap.0);
+            ui.label("→");
+            ui.text_edit_singleline(&mut self.new_remap.1);
+            if ui.button("Add remap").clicked() && !self.new_remap.0.is_empty() {
+                let remap = std::mem::take(&mut self.new_remap);
+                resolver.remaps.push(remap);
+                changed = true;
+            }
+        });
+
+        changed
+    }
+}
+
+/// Language of the highlighter for a source file, C/C++ sources come from FFI frames.
+fn language_of(file_name: &str) -> Option<&'static str> {
+    let extension = Path::new(file_name).extension()?.to_str()?;
+
+    match extension {
+        "rs" => Some("rs"),
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Some("cpp"),
+        _ => None,
+    }
+}
@@ -0,0 +1,302 @@
+use crate::ui::MemInfo;
+use crate::ui::calltree::Cost;
+use crate::ui::flamegraph::MemoryKind;
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::stack::owner_frame;
+use crate::ui::symbols::{self, SymbolOptions};
+use crate::ui::widgets::flamegraph::hashed_color;
+use crate::ui::widgets::treemap::{Treemap, TreemapItem};
+use bytesize::ByteSize;
+use egui::{Align, ComboBox, DragValue, Layout, RichText, Ui, vec2};
+use egui_extras::{Column, TableBuilder};
+use memtrace_utils::parser::AccumulatedData;
+use std::collections::{BTreeMap, HashMap};
+
+const UNKNOWN: &str = "<unknown>";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Grouping {
+    Crate,
+    Module,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum SortColumn {
+    Name,
+    Peak,
+    Leaked,
+    Allocations,
+    Temporary,
+}
+
+pub struct Group {
+    pub name: String,
+    pub cost: Cost,
+}
+
+/// Costs attributed to crates or modules, answering how much memory a dependency costs.
+pub struct CratesPage {
+    grouping: Grouping,
+    depth: usize,
+    metric: MemoryKind,
+    sort: SortColumn,
+    descending: bool,
+    groups: Vec<Group>,
+    computed: Option<(Grouping, usize)>,
+}
+
+impl CratesPage {
+    pub fn new() -> Self {
+        Self {
+            grouping: Grouping::Crate,
+            depth: 2,
+            metric: MemoryKind::Peak,
+            sort: SortColumn::Peak,
+            descending: true,
+            groups: Vec::new(),
+            computed: None,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, info: &MemInfo) {
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Group by")
+                .selected_text(format!("{:?}", self.grouping))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.grouping, Grouping::Crate, "Crate");
+                    ui.selectable_value(&mut self.grouping, Grouping::Module, "Module");
+                });
+
+            if self.grouping == Grouping::Module {
+                ui.add(
+                    DragValue::new(&mut self.depth)
+                        .range(1..=8)
+                        .prefix("depth: "),
+                );
+            }
+
+            ComboBox::from_label("Treemap size")
+                .selected_text(format!("{:?}", self.metric))
+                .show_ui(ui, |ui| {
+                    for kind in MemoryKind::ALL {
+                        ui.selectable_value(&mut self.metric, kind, format!("{:?}", kind));
+                    }
+                });
+        });
+        ui.label(
+            RichText::new(
+                "Allocations are attributed to the innermost frame outside the standard library.",
+            )
+            .weak(),
+        );
+        ui.separator();
+
+        let key = (self.grouping, self.depth);
+        if self.computed != Some(key) {
+            self.groups = group_allocations(&info.data, self.grouping, self.depth);
+            self.computed = Some(key);
+            self.sort_groups();
+        }
+
+        let available = ui.available_size();
+        ui.horizontal(|ui| {
+            ui.allocate_ui(vec2(available.x * 0.45, available.y), |ui| {
+                self.show_table(ui);
+            });
+            ui.separator();
+            ui.allocate_ui(ui.available_size(), |ui| {
+                self.show_treemap(ui);
+            });
+        });
+    }
+
+    fn show_table(&mut self, ui: &mut Ui) {
+        const HEIGHT: f32 = 20.0;
+
+        let mut clicked = None;
+        TableBuilder::new(ui)
+            .id_salt("crates_table")
+            .striped(true)
+            .resizable(true)
+            .auto_shrink(false)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .columns(Column::auto().at_least(80.0), 4)
+            .header(HEIGHT, |mut header| {
+                for column in [
+                    SortColumn::Name,
+                    SortColumn::Peak,
+                    SortColumn::Leaked,
+                    SortColumn::Allocations,
+                    SortColumn::Temporary,
+                ] {
+                    header.col(|ui| {
+                        let arrow = match (self.sort == column, self.descending) {
+                            (true, true) => " ⏷",
+                            (true, false) => " ⏶",
+                            (false, _) => "",
+                        };
+                        if ui.button(format!("{:?}{}", column, arrow)).clicked() {
+                            clicked = Some(column);
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(HEIGHT, self.groups.len(), |mut row| {
+                    let group = &self.groups[row.index()];
+                    row.col(|ui| {
+                        ui.label(&group.name);
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(group.cost.peaked).to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(group.cost.leaked).to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(group.cost.allocations.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(group.cost.temporary.to_string());
+                    });
+                });
+            });
+
+        if let Some(column) = clicked {
+            if self.sort == column {
+                self.descending = !self.descending;
+            } else {
+                self.sort = column;
+                self.descending = column != SortColumn::Name;
+            }
+            self.sort_groups();
+        }
+    }
+
+    fn show_treemap(&mut self, ui: &mut Ui) {
+        let metric = self.metric;
+        let items = make_treemap_items(&self.groups, metric);
+        let total = items.iter().map(|item| item.value).sum::<f64>();
+        let format_value = |value: f64| metric.format(value);
+
+        let response = Treemap::new(&items, &format_value).show(ui);
+
+        if let Some(path) = response.hovered {
+            response.response.on_hover_ui_at_pointer(|ui| {
+                let mut items = items.as_slice();
+                let mut names = Vec::new();
+                let mut value = 0.0;
+                for idx in path {
+                    names.push(items[idx].label.as_str());
+                    value = items[idx].value;
+                    items = &items[idx].children;
+                }
+
+                ui.label(RichText::new(names.join("::")).strong());
+                ui.label(format!(
+                    "{:?}: {} ({:.2}%)",
+                    metric,
+                    metric.format(value),
+                    if total > 0.0 {
+                        value / total * 100.0
+                    } else {
+                        0.0
+                    }
+                ));
+            });
+        }
+    }
+
+    fn sort_groups(&mut self) {
+        let sort = self.sort;
+        self.groups.sort_by(|a, b| match sort {
+            SortColumn::Name => a.name.cmp(&b.name),
+            SortColumn::Peak => a.cost.peaked.cmp(&b.cost.peaked),
+            SortColumn::Leaked => a.cost.leaked.cmp(&b.cost.leaked),
+            SortColumn::Allocations => a.cost.allocations.cmp(&b.cost.allocations),
+            SortColumn::Temporary => a.cost.temporary.cmp(&b.cost.temporary),
+        });
+        if self.descending {
+            self.groups.reverse();
+        }
+    }
+}
+
+/// Sums the costs of every allocation by the crate or module of its innermost frame outside
+/// the standard library, or of the allocation site when the whole stack is std.
+pub fn group_allocations(data: &AccumulatedData, grouping: Grouping, depth: usize) -> Vec<Group> {
+    let options = SymbolOptions {
+        demangle: true,
+        strip_hashes: true,
+        collapse_generics: false,
+        trim_closures: false,
+    };
+
+    let mut costs: HashMap<String, Cost> = HashMap::new();
+    for allocation in &data.allocations {
+        let owner = owner_frame(data, allocation.trace_idx)
+            .map(|frame| fn_name_from_frame(&data.strings, Some(frame)));
+
+        let name = symbols::prettify(owner.unwrap_or_default(), options);
+        let key = if name.is_empty() {
+            UNKNOWN.to_string()
+        } else {
+            match grouping {
+                Grouping::Crate => symbols::crate_name(&name).to_string(),
+                Grouping::Module => symbols::module_path(&name, depth),
+            }
+        };
+
+        costs.entry(key).or_default().add(&Cost::of(allocation));
+    }
+
+    costs
+        .into_iter()
+        .map(|(name, cost)| Group { name, cost })
+        .collect()
+}
+
+/// Crates with their modules nested inside.
+fn make_treemap_items(groups: &[Group], metric: MemoryKind) -> Vec<TreemapItem> {
+    let mut crates: BTreeMap<&str, Vec<(&str, f64)>> = BTreeMap::new();
+
+    for group in groups {
+        let value = metric.cost_value(&group.cost);
+        let (crate_name, module) = group.name.split_once("::").unwrap_or((&group.name, ""));
+        crates.entry(crate_name).or_default().push((module, value));
+    }
+
+    crates
+        .into_iter()
+        .map(|(crate_name, modules)| {
+            let children = match modules.as_slice() {
+                [("", _)] => Vec::new(),
+                _ => modules
+                    .iter()
+                    .map(|(module, value)| {
+                        // functions at the crate root
+                        let label = if module.is_empty() {
+                            crate_name
+                        } else {
+                            module
+                        };
+                        TreemapItem {
+                            label: label.to_string(),
+                            value: *value,
+                            color: hashed_color(label),
+                            children: Vec::new(),
+                        }
+                    })
+                    .collect(),
+            };
+
+            TreemapItem {
+                label: crate_name.to_string(),
+                value: modules.iter().map(|(_, value)| value).sum(),
+                color: hashed_color(crate_name),
+                children,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,430 @@
+use crate::ui::MemInfo;
+use crate::ui::calltree::string;
+use crate::ui::events::{EventKind, Events};
+use crate::ui::helpers::add_key_value;
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::stack::{
+    Navigation, StackRef, frame_file, frame_location, ip_inline_frames, trace_ips,
+};
+use crate::ui::symbols::{self, SymbolOptions};
+use bytesize::ByteSize;
+use egui::{Context, RichText, ScrollArea, Sense, Ui, pos2, vec2};
+use memtrace_utils::parser::{AccumulatedData, AllocationData};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, channel};
+use std::thread;
+use std::time::Duration;
+
+/// Number of bars of the live bytes timeline.
+const TIMELINE_SLOTS: usize = 200;
+
+struct DetailFrame {
+    function: String,
+    location: Option<(String, u32)>,
+    module: String,
+    /// Inlined into the next frame rather than called from it.
+    inlined: bool,
+}
+
+/// Live bytes of an allocation in a slot of the timeline in which they changed.
+#[derive(Clone, Copy)]
+struct SlotChange {
+    slot: u16,
+    /// Highest live bytes in the slot.
+    max: u64,
+    /// Live bytes at the end of the slot.
+    end: u64,
+}
+
+/// Allocation counts and live bytes of every allocation, computed from the events once rather
+/// than for each site shown.
+pub struct EventStats {
+    /// Number of allocations made with each allocation info.
+    alloc_counts: Vec<u64>,
+    /// Slots in which the live bytes of each allocation changed, in order.
+    changes: Vec<Vec<SlotChange>>,
+    /// Milliseconds of trace covered by the timeline.
+    end_time: u64,
+}
+
+impl EventStats {
+    pub fn new(data: &AccumulatedData, events: &Events) -> Self {
+        let end_time = events.end_time();
+        let mut changes = vec![Vec::<SlotChange>::new(); data.allocations.len()];
+
+        for event in events.iter() {
+            let Some(alloc_info) = data.allocation_infos.get(event.info_idx as usize) else {
+                continue;
+            };
+            let Some(changes) = changes.get_mut(alloc_info.allocation_idx as usize) else {
+                continue;
+            };
+
+            let slot = (event.time * TIMELINE_SLOTS as u64 / (end_time + 1)) as u16;
+            let live = changes.last().map_or(0, |change| change.end);
+            if changes.last().is_none_or(|change| change.slot != slot) {
+                changes.push(SlotChange {
+                    slot,
+                    max: live,
+                    end: live,
+                });
+            }
+
+            let Some(change) = changes.last_mut() else {
+                continue;
+            };
+            change.end = match event.kind {
+                EventKind::Alloc => live + alloc_info.size,
+                EventKind::Free => live.saturating_sub(alloc_info.size),
+            };
+            change.max = change.max.max(change.end);
+        }
+
+        Self {
+            alloc_counts: events.alloc_counts(data.allocation_infos.len()),
+            changes,
+            end_time,
+        }
+    }
+
+    /// Sum of the highest live bytes of each of the `matched` allocations in each slot.
+    fn timeline(&self, matched: &[bool]) -> Vec<u64> {
+        let mut timeline = vec![0; TIMELINE_SLOTS];
+        // live bytes kept through the slots without changes, as differences to the previous slot
+        let mut kept = vec![0i64; TIMELINE_SLOTS + 1];
+
+        let changes = self.changes.iter().zip(matched).filter(|(_, m)| **m);
+        for (changes, _) in changes {
+            let (mut live, mut next) = (0, 0);
+            for change in changes {
+                let slot = change.slot as usize;
+                kept[next] += live as i64;
+                kept[slot] -= live as i64;
+                timeline[slot] += change.max;
+                (live, next) = (change.end, slot + 1);
+            }
+            kept[next] += live as i64;
+            kept[TIMELINE_SLOTS] -= live as i64;
+        }
+
+        let mut live = 0;
+        for (value, diff) in timeline.iter_mut().zip(&kept) {
+            live += diff;
+            *value += live as u64;
+        }
+        timeline
+    }
+}
+
+/// Details window of a site, whose details are built on a background thread as matching the
+/// allocations of the site walks all of their stacks.
+pub struct DetailsWindow {
+    details: Option<SiteDetails>,
+    receiver: Receiver<SiteDetails>,
+}
+
+impl DetailsWindow {
+    pub fn open(ctx: &Context, info: &Arc<MemInfo>, stack: StackRef) -> Self {
+        let (sender, receiver) = channel();
+
+        let ctx = ctx.clone();
+        let info = info.clone();
+        thread::spawn(move || {
+            if sender.send(SiteDetails::new(&info, stack)).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            details: None,
+            receiver,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        if self.details.is_none() {
+            self.details = self.receiver.try_recv().ok();
+        }
+
+        match &self.details {
+            Some(details) => details.show(ui, info, symbol_options),
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Collecting the allocations of the site…");
+                });
+                None
+            }
+        }
+    }
+}
+
+/// Everything known about the allocations made through a call stack.
+pub struct SiteDetails {
+    stack: StackRef,
+    /// Frames of the stack, innermost first.
+    frames: Vec<DetailFrame>,
+    cost: AllocationData,
+    /// Allocations and their bytes, as recorded by the allocation events.
+    blocks: u64,
+    bytes: u64,
+    min_size: Option<u64>,
+    max_size: u64,
+    /// Live bytes of the site in each slot of the trace duration, summing the highest live
+    /// bytes of each of its allocations in the slot.
+    timeline: Vec<u64>,
+    /// Milliseconds of trace covered by the timeline.
+    end_time: u64,
+    events_error: Option<String>,
+}
+
+impl SiteDetails {
+    pub fn new(info: &MemInfo, stack: StackRef) -> Self {
+        let data = &info.data;
+        let matched = matching_allocations(data, stack);
+
+        let mut cost = AllocationData::default();
+        for (allocation, _) in data.allocations.iter().zip(&matched).filter(|(_, m)| **m) {
+            cost.allocations += allocation.data.allocations;
+            cost.temporary += allocation.data.temporary;
+            cost.leaked += allocation.data.leaked;
+            cost.peak += allocation.data.peak;
+        }
+
+        let (stats, events_error) = match info.event_stats() {
+            Ok(stats) => (Some(stats), None),
+            Err(err) => (
+                None,
+                Some(format!("Failed to read allocation events: {err}")),
+            ),
+        };
+
+        let counts = stats.map_or(&[][..], |stats| &stats.alloc_counts);
+        let (mut blocks, mut bytes, mut min_size, mut max_size) = (0, 0, None::<u64>, 0);
+        for (alloc_info, count) in data.allocation_infos.iter().zip(counts) {
+            if *count == 0 || !matched[alloc_info.allocation_idx as usize] {
+                continue;
+            }
+            blocks += count;
+            bytes += alloc_info.size * count;
+            min_size = Some(min_size.map_or(alloc_info.size, |min| min.min(alloc_info.size)));
+            max_size = max_size.max(alloc_info.size);
+        }
+
+        let (timeline, end_time) = match stats {
+            Some(stats) => (stats.timeline(&matched), stats.end_time),
+            None => (vec![0; TIMELINE_SLOTS], 0),
+        };
+
+        Self {
+            stack,
+            frames: stack_frames(data, stack),
+            cost,
+            blocks,
+            bytes,
+            min_size,
+            max_size,
+            timeline,
+            end_time,
+            events_error,
+        }
+    }
+
+    pub fn show(
+        &self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        let data = &info.data;
+        let mut navigation = None;
+
+        if let Some(site) = self.frames.first() {
+            ui.heading(symbols::prettify(&site.function, symbol_options).as_ref());
+            add_key_value(ui, "module", &site.module);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Show in TopDown").clicked() {
+                navigation = Some(Navigation::TopDown(self.stack));
+            }
+            if ui.button("Show in Flamegraph").clicked() {
+                navigation = Some(Navigation::Flamegraph(self.stack));
+            }
+            if ui.button("Copy stack").clicked() {
+                ui.ctx().copy_text(self.stack.to_text(data));
+            }
+        });
+        ui.separator();
+
+        ui.columns(2, |columns| {
+            add_key_value(&mut columns[0], "allocations", self.cost.allocations);
+            add_key_value(&mut columns[0], "temporary", self.cost.temporary);
+            add_key_value(&mut columns[0], "peak", ByteSize::b(self.cost.peak));
+            add_key_value(&mut columns[0], "leaked", ByteSize::b(self.cost.leaked));
+
+            let col = &mut columns[1];
+            add_key_value(col, "allocated", ByteSize::b(self.bytes));
+            match self.min_size {
+                Some(min_size) => {
+                    add_key_value(col, "min size", ByteSize::b(min_size));
+                    add_key_value(col, "avg size", ByteSize::b(self.bytes / self.blocks));
+                    add_key_value(col, "max size", ByteSize::b(self.max_size));
+                }
+                None => match &self.events_error {
+                    Some(error) => {
+                        col.colored_label(col.visuals().error_fg_color, error);
+                    }
+                    None => {
+                        col.label(RichText::new("No allocation events recorded").weak());
+                    }
+                },
+            }
+        });
+
+        ui.separator();
+        ui.label(RichText::new("Live bytes").strong());
+        self.show_timeline(ui);
+
+        ui.separator();
+        ScrollArea::vertical()
+            .id_salt("site_stack")
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for frame in &self.frames {
+                    let name = symbols::prettify(&frame.function, symbol_options);
+                    ui.horizontal(|ui| {
+                        let mut text = RichText::new(name.as_ref());
+                        if frame.inlined {
+                            text = text.italics();
+                        }
+                        ui.label(text)
+                            .on_hover_text(format!("{}\n{}", frame.function, frame.module));
+                        if frame.inlined {
+                            ui.label(RichText::new("inlined").small().weak());
+                        }
+                    });
+                    if let Some((file_name, line_number)) = &frame.location
+                        && ui
+                            .link(RichText::new(format!("    at {file_name}:{line_number}")).weak())
+                            .clicked()
+                    {
+                        navigation = Some(Navigation::OpenInEditor {
+                            file_name: file_name.clone(),
+                            line_number: *line_number,
+                        });
+                    }
+                }
+            });
+
+        navigation
+    }
+
+    fn show_timeline(&self, ui: &mut Ui) {
+        let size = vec2(ui.available_width(), 60.0);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        let max = self.timeline.iter().copied().max().unwrap_or(0).max(1);
+        let width = rect.width() / self.timeline.len() as f32;
+
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        for (idx, live) in self.timeline.iter().enumerate() {
+            let height = *live as f32 / max as f32 * rect.height();
+            let x = rect.min.x + idx as f32 * width;
+            let bar =
+                egui::Rect::from_min_max(pos2(x, rect.max.y - height), pos2(x + width, rect.max.y));
+            painter.rect_filled(bar, 0.0, ui.visuals().selection.bg_fill);
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let idx = (((pos.x - rect.min.x) / width) as usize).min(self.timeline.len() - 1);
+            let time = idx as u64 * (self.end_time + 1) / self.timeline.len() as u64;
+            response.on_hover_text_at_pointer(format!(
+                "{:.1?}: {}",
+                Duration::from_millis(time),
+                ByteSize::b(self.timeline[idx])
+            ));
+        }
+    }
+}
+
+/// Whether each allocation was made through the frames of the stack.
+fn matching_allocations(data: &AccumulatedData, stack: StackRef) -> Vec<bool> {
+    let target = stack
+        .frames(data)
+        .into_iter()
+        .map(frame_location)
+        .collect::<Vec<_>>();
+
+    data.allocations
+        .iter()
+        .map(|allocation| {
+            let frames = StackRef {
+                trace_idx: allocation.trace_idx,
+                depth: target.len(),
+            }
+            .frames(data);
+            frames.len() == target.len()
+                && frames
+                    .into_iter()
+                    .map(frame_location)
+                    .eq(target.iter().copied())
+        })
+        .collect()
+}
+
+/// Frames of the stack, innermost first, with the module of their instruction pointer.
+fn stack_frames(data: &AccumulatedData, stack: StackRef) -> Vec<DetailFrame> {
+    let mut frames = Vec::new();
+    for ip_info in trace_ips(data, stack.trace_idx) {
+        let module = string(data, ip_info.module_idx);
+        for (frame, inlined) in ip_inline_frames(ip_info) {
+            frames.push(DetailFrame {
+                function: fn_name_from_frame(&data.strings, Some(frame)).to_string(),
+                location: frame_file(data, frame)
+                    .map(|(file_name, line_number)| (file_name.to_string(), line_number)),
+                module: module.to_string(),
+                inlined,
+            });
+        }
+    }
+
+    frames.drain(..frames.len().saturating_sub(stack.depth));
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memtrace_utils::parser::{Allocation, AllocationInfo};
+
+    #[test]
+    fn timeline_keeps_live_bytes_between_events() {
+        let mut data = AccumulatedData::new();
+        data.allocations = (1..=2).map(Allocation::new).collect();
+        data.allocation_infos = vec![AllocationInfo::new(0, 8), AllocationInfo::new(1, 16)];
+
+        // 8 bytes from the start, replaced by 16 bytes at 100ms and freed at 200ms
+        let trace = "+ 0\nc 64\n+ 1\n- 0\nc c8\n- 1\n";
+        let events = Events::parse(trace.as_bytes()).expect("valid trace");
+        let stats = EventStats::new(&data, &events);
+        assert_eq!(stats.alloc_counts, [1, 1]);
+
+        let timeline = stats.timeline(&[true, true]);
+        assert_eq!(timeline[0], 8);
+        assert_eq!(timeline[98], 8);
+        assert_eq!(timeline[99], 8 + 16);
+        assert_eq!(timeline[100], 16);
+        assert_eq!(timeline[199], 16);
+
+        let timeline = stats.timeline(&[false, true]);
+        assert_eq!(timeline[98], 0);
+        assert_eq!(timeline[99], 16);
+        assert_eq!(timeline[198], 16);
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+/// Command line that opens a source location in an external editor.
+///
+/// `{file}` and `{line}` are replaced with the location and arguments written as `$VAR` are
+/// taken from the environment, e.g. `code -g {file}:{line}` or `$EDITOR +{line} {file}`.
+#[derive(Debug, Clone)]
+pub struct EditorCommand {
+    template: String,
+}
+
+impl EditorCommand {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Falls back to `$VISUAL` or `$EDITOR` when no template is configured. These usually name
+    /// terminal editors, so they are only used when started from a terminal for the editor to
+    /// run in, GUI editors need a template.
+    pub fn from_env() -> Option<Self> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+
+        ["VISUAL", "EDITOR"]
+            .into_iter()
+            .find(|var| env::var(var).is_ok_and(|v| !v.trim().is_empty()))
+            .map(|var| Self::new(format!("${var} +{{line}} {{file}}")))
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn open(&self, file: &Path, line: u32) -> io::Result<()> {
+        let file = file.to_string_lossy();
+        let line = line.max(1).to_string();
+
+        let mut args = Vec::new();
+        for arg in self.template.split_whitespace() {
+            // an editor variable may carry its own arguments, e.g. EDITOR="code -w"
+            if let Some(var) = arg.strip_prefix('$')
+                && let Ok(value) = env::var(var)
+            {
+                args.extend(value.split_whitespace().map(str::to_string));
+                continue;
+            }
+
+            args.push(arg.replace("{file}", &file).replace("{line}", &line));
+        }
+
+        let Some((program, args)) = args.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "empty editor command",
+            ));
+        };
+
+        let mut child = Command::new(program).args(args).spawn()?;
+        // reap the editor once it exits, GUI editors often return right away
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
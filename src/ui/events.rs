@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Bit of a stored event telling a free from an allocation.
+const FREE_BIT: u32 = 1 << 31;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Alloc,
+    Free,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Index into `AccumulatedData::allocation_infos`, which holds the size and the trace.
+    pub info_idx: u32,
+    /// Milliseconds since the start of the program, as of the last timestamp before the event.
+    pub time: u64,
+}
+
+/// A timestamp of the trace and the first event recorded after it.
+#[derive(Debug, Clone, Copy)]
+struct Timestamp {
+    first_event: usize,
+    time: u64,
+}
+
+/// Allocations and frees in the order they happened, which `AccumulatedData` only keeps as
+/// totals per trace.
+///
+/// Traces hold millions of events, so each one is stored as its allocation info index with the
+/// kind in the top bit, and times are stored once per timestamp.
+#[derive(Default)]
+pub struct Events {
+    events: Vec<u32>,
+    timestamps: Vec<Timestamp>,
+}
+
+impl Events {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(BufReader::new(File::open(path)?))
+    }
+
+    pub fn parse(reader: impl BufRead) -> io::Result<Self> {
+        let mut events = Vec::new();
+        let mut timestamps = Vec::new();
+
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut split = line.split_whitespace();
+            let kind_bit = match split.next() {
+                Some("+") => 0,
+                Some("-") => FREE_BIT,
+                Some("c") => {
+                    let time = parse_hex(split.next(), n)?;
+                    // only the last of several timestamps without events in between is needed
+                    if timestamps
+                        .last()
+                        .is_some_and(|last: &Timestamp| last.first_event == events.len())
+                    {
+                        timestamps.pop();
+                    }
+                    timestamps.push(Timestamp {
+                        first_event: events.len(),
+                        time,
+                    });
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let info_idx = parse_hex(split.next(), n)?;
+            if info_idx >= FREE_BIT as u64 {
+                return Err(invalid_event(n));
+            }
+            events.push(info_idx as u32 | kind_bit);
+        }
+
+        Ok(Self { events, timestamps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events in the order they happened.
+    pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
+        let mut timestamps = self.timestamps.iter().peekable();
+        let mut time = 0;
+
+        self.events.iter().enumerate().map(move |(idx, event)| {
+            while let Some(timestamp) = timestamps.next_if(|t| t.first_event <= idx) {
+                time = timestamp.time;
+            }
+
+            Event {
+                kind: match event & FREE_BIT {
+                    0 => EventKind::Alloc,
+                    _ => EventKind::Free,
+                },
+                info_idx: event & !FREE_BIT,
+                time,
+            }
+        })
+    }
+
+    /// Time of the last event.
+    pub fn end_time(&self) -> u64 {
+        self.timestamps
+            .iter()
+            .rev()
+            .find(|timestamp| timestamp.first_event < self.events.len())
+            .map_or(0, |timestamp| timestamp.time)
+    }
+
+    /// Number of allocations made with each of the `infos` allocation infos.
+    pub fn alloc_counts(&self, infos: usize) -> Vec<u64> {
+        let mut counts = vec![0; infos];
+        for event in self.iter() {
+            if event.kind == EventKind::Alloc
+                && let Some(count) = counts.get_mut(event.info_idx as usize)
+            {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    /// Blocks of each of the `infos` allocation infos still allocated at the end of the trace.
+    pub fn live_counts(&self, infos: usize) -> Vec<u64> {
+        let mut counts = vec![0u64; infos];
+        for event in self.iter() {
+            if let Some(count) = counts.get_mut(event.info_idx as usize) {
+                match event.kind {
+                    EventKind::Alloc => *count += 1,
+                    EventKind::Free => *count = count.saturating_sub(1),
+                }
+            }
+        }
+        counts
+    }
+}
+
+fn parse_hex(value: Option<&str>, line: usize) -> io::Result<u64> {
+    value
+        .and_then(|value| u64::from_str_radix(value, 16).ok())
+        .ok_or_else(|| invalid_event(line))
+}
+
+fn invalid_event(line: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid event at line {}", line + 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(trace: &str) -> Vec<(EventKind, u32, u64)> {
+        Events::parse(trace.as_bytes())
+            .expect("valid trace")
+            .iter()
+            .map(|event| (event.kind, event.info_idx, event.time))
+            .collect()
+    }
+
+    #[test]
+    fn events_take_the_time_of_the_last_timestamp() {
+        let trace = "v 1\n+ 0\nc 10\nc 20\n+ 1\n- 0\nt 1 0\nc 30\nc 40\n- 1\nc 50\n";
+        assert_eq!(
+            parse(trace),
+            [
+                (EventKind::Alloc, 0, 0),
+                (EventKind::Alloc, 1, 0x20),
+                (EventKind::Free, 0, 0x20),
+                (EventKind::Free, 1, 0x40),
+            ]
+        );
+
+        let events = Events::parse(trace.as_bytes()).unwrap();
+        assert_eq!(events.end_time(), 0x40);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_events() {
+        for trace in ["+ 0\n+ x\n", "+ 0\n-\n", "+ 0\nc\n", "+ 0\n+ 80000000\n"] {
+            let err = Events::parse(trace.as_bytes()).err().expect(trace);
+            assert_eq!(err.to_string(), "invalid event at line 2", "{trace}");
+        }
+    }
+}
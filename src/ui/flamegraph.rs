@@ -1,8 +1,26 @@
+use crate::ui::calltree::{CallTree, Cost, ROOT};
 use crate::ui::overview::fn_name_from_frame;
-use crate::ui::widgets::flamegraph::{Flamegraph, Options};
-use crate::ui::MemInfo;
-use egui::{ComboBox, Ui};
-use memtrace_utils::parser::{AccumulatedData, Allocation, InstructionPointer};
+use crate::ui::stack::{
+    Navigation, StackRef, frame_file, ip_frames, stack_menu, trace_inline_frames,
+};
+use crate::ui::symbols;
+use crate::ui::symbols::Origin;
+use crate::ui::widgets::flamegraph::{
+    FlameTree, Flamegraph, FoldedTree, HoveredFrame, Options, hashed_color, heat_color,
+};
+use crate::ui::{MemInfo, Settings};
+use bytesize::ByteSize;
+use egui::{Color32, ComboBox, Grid, RichText, TextEdit, Ui, Window};
+use memtrace_utils::parser::{AccumulatedData, Allocation, Frame};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Suffix of the labels of inlined functions, as written by `inferno-collapse-perf`.
+const INLINED_SUFFIX: &str = "_[i]";
 
 struct Line {
     frames: Vec<String>,
@@ -10,13 +28,63 @@ struct Line {
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
-enum MemoryKind {
+pub enum MemoryKind {
     Peak,
     Allocations,
     Temporary,
     Leaked,
 }
 
+impl MemoryKind {
+    pub const ALL: [MemoryKind; 4] = [
+        MemoryKind::Peak,
+        MemoryKind::Temporary,
+        MemoryKind::Leaked,
+        MemoryKind::Allocations,
+    ];
+
+    fn value(self, allocation: &Allocation) -> f64 {
+        match self {
+            MemoryKind::Peak => allocation.data.peak as f64,
+            MemoryKind::Allocations => allocation.data.allocations as f64,
+            MemoryKind::Temporary => allocation.data.temporary as f64,
+            MemoryKind::Leaked => allocation.data.leaked as f64,
+        }
+    }
+
+    pub fn cost_value(self, cost: &Cost) -> f64 {
+        match self {
+            MemoryKind::Peak => cost.peaked as f64,
+            MemoryKind::Allocations => cost.allocations as f64,
+            MemoryKind::Temporary => cost.temporary as f64,
+            MemoryKind::Leaked => cost.leaked as f64,
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            MemoryKind::Peak | MemoryKind::Leaked => ByteSize::b(value as u64).to_string(),
+            MemoryKind::Allocations | MemoryKind::Temporary => format!("{}", value),
+        }
+    }
+}
+
+impl FromStr for MemoryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "peak" => Ok(MemoryKind::Peak),
+            "allocations" => Ok(MemoryKind::Allocations),
+            "temporary" => Ok(MemoryKind::Temporary),
+            "leaked" => Ok(MemoryKind::Leaked),
+            _ => Err(format!(
+                "unknown memory kind '{s}', expected one of: peak, leaked, allocations, temporary"
+            )),
+        }
+    }
+}
+
 impl Line {
     pub fn new(value: f64) -> Self {
         Self {
@@ -37,12 +105,164 @@ impl Line {
     }
 }
 
+/// Stacks in Brendan Gregg's folded format, one `frame;frame;frame value` per line.
+pub struct FoldedFile {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+impl FoldedFile {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        let mut lines = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let valid = line.rsplit_once(' ').is_some_and(|(frames, value)| {
+                !frames.trim().is_empty() && value.parse::<f64>().is_ok_and(f64::is_finite)
+            });
+            if !valid {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid folded stack at line {}", n + 1),
+                ));
+            }
+
+            lines.push(line.to_string());
+        }
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Self { name, lines })
+    }
+}
+
+pub fn write_folded(lines: &[String], path: impl AsRef<Path>) -> io::Result<()> {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    fs::write(path, content)
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum ColorScheme {
+    Function,
+    Crate,
+    Module,
+    File,
+    Origin,
+    Heat,
+}
+
+impl ColorScheme {
+    const ALL: [ColorScheme; 6] = [
+        ColorScheme::Function,
+        ColorScheme::Crate,
+        ColorScheme::Module,
+        ColorScheme::File,
+        ColorScheme::Origin,
+        ColorScheme::Heat,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Function => "By function",
+            ColorScheme::Crate => "By crate",
+            ColorScheme::Module => "By module",
+            ColorScheme::File => "By source file",
+            ColorScheme::Origin => "User vs std/third-party",
+            ColorScheme::Heat => "Heat",
+        }
+    }
+
+    fn color(self, label: &str, share: f64, file_name: Option<&str>) -> Color32 {
+        match self {
+            ColorScheme::Function => hashed_color(label),
+            ColorScheme::Crate => hashed_color(symbols::crate_name(label)),
+            ColorScheme::Module => hashed_color(symbols::module_path(label, usize::MAX)),
+            ColorScheme::File => match file_name {
+                Some(file_name) => hashed_color(file_name),
+                None => hashed_color(label),
+            },
+            ColorScheme::Origin => match symbols::origin(label, file_name) {
+                Origin::User => Color32::from_rgb(120, 200, 120),
+                Origin::Std => Color32::from_rgb(150, 180, 220),
+                Origin::ThirdParty => Color32::from_rgb(230, 170, 100),
+            },
+            ColorScheme::Heat => heat_color(share),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Source {
+    Memory(MemoryKind),
+    Folded(usize),
+}
+
+#[derive(PartialEq)]
+enum DialogKind {
+    Export,
+    Open,
+}
+
+struct PathDialog {
+    kind: DialogKind,
+    path: String,
+    error: Option<String>,
+}
+
+/// Call tree of the allocations as flamegraph frames sized by one kind of memory.
+struct MemoryTree<'a> {
+    data: &'a AccumulatedData,
+    tree: &'a CallTree,
+    kind: MemoryKind,
+}
+
+impl FlameTree for MemoryTree<'_> {
+    fn children(&self, id: u32) -> &[u32] {
+        &self.tree.node(id).children
+    }
+
+    fn parent(&self, id: u32) -> u32 {
+        self.tree.node(id).parent
+    }
+
+    fn value(&self, id: u32) -> f64 {
+        self.kind.cost_value(&self.tree.node(id).cost)
+    }
+
+    fn label(&self, id: u32) -> Cow<'_, str> {
+        let name = self.tree.name(self.data, id);
+        if self.tree.node(id).inlined {
+            Cow::Owned(format!("{name}{INLINED_SUFFIX}"))
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+
+    fn max_depth(&self) -> u32 {
+        self.tree.max_depth()
+    }
+}
+
 pub struct FlamegraphPage {
-    memory_kind: MemoryKind,
-    peak_frame_lines: Vec<String>,
-    tmp_frame_lines: Vec<String>,
-    allocations_frame_lines: Vec<String>,
-    leaked_frame_lines: Vec<String>,
+    source: Source,
+    color_scheme: ColorScheme,
+    folded_files: Vec<FoldedFile>,
+    /// Trees of `folded_files`, built with `collapse_recursion`.
+    folded_trees: Vec<FoldedTree>,
+    collapse_recursion: bool,
+    file_by_function: HashMap<String, String>,
+    dialog: Option<PathDialog>,
+    context_frame: Option<HoveredFrame>,
     flamegraph: Flamegraph,
 }
 
@@ -52,96 +272,438 @@ impl FlamegraphPage {
 
         let fg = Flamegraph::new(options);
 
-        let peak_frame_lines = Self::make_frame_lines(info, |a| a.data.peak as f64);
-        let tmp_frame_lines = Self::make_frame_lines(info, |a| a.data.temporary as f64);
-        let allocations_frame_lines = Self::make_frame_lines(info, |a| a.data.allocations as f64);
-        let leaked_frame_lines = Self::make_frame_lines(info, |a| a.data.leaked as f64);
-
         Self {
-            memory_kind: MemoryKind::Peak,
-            peak_frame_lines,
-            tmp_frame_lines,
-            allocations_frame_lines,
-            leaked_frame_lines,
+            source: Source::Memory(MemoryKind::Peak),
+            color_scheme: ColorScheme::Function,
+            file_by_function: make_file_by_function(&info.data),
+            folded_files: Vec::new(),
+            folded_trees: Vec::new(),
+            collapse_recursion: false,
+            dialog: None,
+            context_frame: None,
             flamegraph: fg,
         }
     }
 
-    pub fn show(&mut self, ui: &mut Ui) {
-        let prev_memory_kind = self.memory_kind;
+    pub fn add_folded_file(&mut self, file: FoldedFile) {
+        let lines = file.lines.iter().map(String::as_str);
+        self.folded_trees
+            .push(FoldedTree::new(lines, self.collapse_recursion));
+        self.folded_files.push(file);
+        self.source = Source::Folded(self.folded_files.len() - 1);
+        self.flamegraph.reset();
+    }
+
+    pub fn focus(&mut self, info: &MemInfo, tree: &CallTree, stack: StackRef) {
+        if let Source::Folded(_) = self.source {
+            self.source = Source::Memory(MemoryKind::Peak);
+        }
+
+        self.flamegraph.focus(tree.find(&stack.frames(&info.data)));
+    }
+
+    fn source_name(&self, source: Source) -> String {
+        match source {
+            Source::Memory(kind) => format!("{:?}", kind),
+            Source::Folded(idx) => self.folded_files[idx].name.clone(),
+        }
+    }
+
+    /// Rebuilds the trees of the folded files, the call tree being rebuilt by the app.
+    fn set_collapse_recursion(&mut self, collapse: bool) {
+        if self.collapse_recursion == collapse {
+            return;
+        }
+
+        self.collapse_recursion = collapse;
+        self.folded_trees = self
+            .folded_files
+            .iter()
+            .map(|file| FoldedTree::new(file.lines.iter().map(String::as_str), collapse))
+            .collect();
+        self.flamegraph.reset();
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        tree: &CallTree,
+        settings: &Settings,
+    ) -> Option<Navigation> {
+        self.set_collapse_recursion(settings.collapse_recursion);
+
+        let prev_source = self.source;
 
-        ComboBox::from_label("")
-            .selected_text(format!("{:?}", self.memory_kind))
-            .show_ui(ui, |ui| {
-                ui.selectable_value(&mut self.memory_kind, MemoryKind::Peak, "Peak");
-                ui.selectable_value(&mut self.memory_kind, MemoryKind::Temporary, "Temporary");
-                ui.selectable_value(&mut self.memory_kind, MemoryKind::Leaked, "Leaked");
-                ui.selectable_value(
-                    &mut self.memory_kind,
-                    MemoryKind::Allocations,
-                    "Allocations",
-                );
+        ui.horizontal(|ui| {
+            ComboBox::from_label("")
+                .selected_text(self.source_name(self.source))
+                .show_ui(ui, |ui| {
+                    let sources = MemoryKind::ALL
+                        .into_iter()
+                        .map(Source::Memory)
+                        .chain((0..self.folded_files.len()).map(Source::Folded));
+                    for source in sources {
+                        let name = self.source_name(source);
+                        ui.selectable_value(&mut self.source, source, name);
+                    }
+                });
+
+            ComboBox::from_id_salt("color_scheme")
+                .selected_text(self.color_scheme.name())
+                .show_ui(ui, |ui| {
+                    for scheme in ColorScheme::ALL {
+                        ui.selectable_value(&mut self.color_scheme, scheme, scheme.name());
+                    }
+                });
+
+            ui.menu_button("Folded stacks", |ui| {
+                if ui.button("Export current…").clicked() {
+                    self.open_dialog(DialogKind::Export);
+                    ui.close_menu();
+                }
+                if ui.button("Open .folded file…").clicked() {
+                    self.open_dialog(DialogKind::Open);
+                    ui.close_menu();
+                }
             });
+        });
+
+        self.show_dialog(ui, info);
 
-        if prev_memory_kind != self.memory_kind {
+        if prev_source != self.source {
             self.flamegraph.reset();
         }
 
         ui.add_space(20.0);
 
-        match self.memory_kind {
-            MemoryKind::Peak => {
-                let frames = self.peak_frame_lines.iter().map(|v| v.as_str());
-                self.flamegraph.show(ui, frames, "bytes");
-            }
-            MemoryKind::Leaked => {
-                let frames = self.leaked_frame_lines.iter().map(|v| v.as_str());
-                self.flamegraph.show(ui, frames, "bytes");
-            }
-            MemoryKind::Temporary => {
-                let frames = self.tmp_frame_lines.iter().map(|v| v.as_str());
-                self.flamegraph.show(ui, frames, "");
+        let source = self.source;
+        let format_value = |value: f64| match source {
+            Source::Memory(kind) => kind.format(value),
+            Source::Folded(_) => format!("{}", value),
+        };
+
+        let color_scheme = self.color_scheme;
+        let file_by_function = &self.file_by_function;
+        let format_label = |label: &str| match label.strip_suffix(INLINED_SUFFIX) {
+            Some(name) => format!("{} [inlined]", symbols::prettify(name, settings.symbols)),
+            None => symbols::prettify(label, settings.symbols).into_owned(),
+        };
+        let color_of = |label: &str, share| {
+            let label = label.strip_suffix(INLINED_SUFFIX).unwrap_or(label);
+            let file_name = file_by_function.get(label).map(|f| f.as_str());
+            color_scheme.color(label, share, file_name)
+        };
+        let response = match source {
+            Source::Memory(kind) => {
+                let tree = MemoryTree {
+                    data: &info.data,
+                    tree,
+                    kind,
+                };
+                self.flamegraph
+                    .show(ui, &tree, format_value, format_label, color_of)
             }
-            MemoryKind::Allocations => {
-                let frames = self.allocations_frame_lines.iter().map(|v| v.as_str());
-                self.flamegraph.show(ui, frames, "");
+            Source::Folded(idx) => self.flamegraph.show(
+                ui,
+                &self.folded_trees[idx],
+                format_value,
+                format_label,
+                color_of,
+            ),
+        };
+
+        if response.response.secondary_clicked() {
+            self.context_frame = response.hovered.clone();
+        }
+
+        let mut navigation = None;
+        response.response.context_menu(|ui| {
+            let Some(frame) = &self.context_frame else {
+                ui.close_menu();
+                return;
+            };
+
+            let Some(stack) = stack_of_frame(tree, source, frame) else {
+                if ui.button("Copy name").clicked() {
+                    ui.ctx().copy_text(frame.label.clone());
+                    ui.close_menu();
+                }
+                return;
+            };
+
+            if let Some(nav) = stack_menu(ui, &info.data, stack) {
+                navigation = Some(nav);
             }
+        });
+
+        if let Some(hovered) = response.hovered
+            && !response.response.context_menu_opened()
+        {
+            response.response.on_hover_ui_at_pointer(|ui| {
+                show_frame_tooltip(ui, info, tree, source, &hovered, format_value);
+            });
         }
+
+        navigation
     }
 
-    fn make_frame_lines(info: &MemInfo, f: impl Fn(&Allocation) -> f64) -> Vec<String> {
+    pub fn make_frame_lines(info: &MemInfo, kind: MemoryKind) -> Vec<String> {
         let mut lines = Vec::new();
 
-        for alloc_info in &info.data.allocation_infos {
-            let allocation = &info.data.allocations[alloc_info.allocation_idx as usize];
-            let mut trace_idx = allocation.trace_idx;
-
-            let value = f(allocation);
+        for allocation in &info.data.allocations {
+            let value = kind.value(allocation);
             let mut line = Line::new(value);
 
-            while trace_idx != 0 {
-                let trace = &info.data.traces[trace_idx as usize - 1];
-                let ip_info = &info.data.instruction_pointers[trace.ip_idx as usize - 1];
+            line.frames.extend(
+                trace_inline_frames(&info.data, allocation.trace_idx)
+                    .map(|(frame, inlined)| frame_label(&info.data, frame, inlined)),
+            );
+            lines.push(line.into_string());
+        }
 
-                let frames = get_frames_from_ip_info(&info.data, ip_info);
-                line.frames.extend(frames);
+        lines
+    }
 
-                trace_idx = trace.parent_idx;
+    fn open_dialog(&mut self, kind: DialogKind) {
+        let path = match (&kind, self.source) {
+            (DialogKind::Export, Source::Memory(kind)) => {
+                format!("{}.folded", format!("{:?}", kind).to_lowercase())
             }
-            lines.push(line.into_string());
+            _ => String::new(),
+        };
+
+        self.dialog = Some(PathDialog {
+            kind,
+            path,
+            error: None,
+        });
+    }
+
+    fn show_dialog(&mut self, ui: &mut Ui, info: &MemInfo) {
+        let Some(dialog) = self.dialog.as_mut() else {
+            return;
+        };
+
+        let title = match dialog.kind {
+            DialogKind::Export => "Export folded stacks",
+            DialogKind::Open => "Open folded stacks",
+        };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    ui.add(TextEdit::singleline(&mut dialog.path).desired_width(300.0));
+                });
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("OK").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+
+        if cancelled {
+            self.dialog = None;
+            return;
         }
 
-        lines
+        if !confirmed {
+            return;
+        }
+
+        let path = dialog.path.trim().to_string();
+        let result = match dialog.kind {
+            DialogKind::Export => match self.source {
+                Source::Memory(kind) => write_folded(&Self::make_frame_lines(info, kind), &path),
+                Source::Folded(idx) => write_folded(&self.folded_files[idx].lines, &path),
+            },
+            DialogKind::Open => FoldedFile::read(&path).map(|file| self.add_folded_file(file)),
+        };
+
+        match result {
+            Ok(()) => self.dialog = None,
+            Err(e) => {
+                if let Some(dialog) = self.dialog.as_mut() {
+                    dialog.error = Some(format!("{path}: {e}"));
+                }
+            }
+        }
+    }
+}
+
+fn make_file_by_function(data: &AccumulatedData) -> HashMap<String, String> {
+    let mut file_by_function = HashMap::new();
+
+    for ip_info in &data.instruction_pointers {
+        for frame in ip_frames(ip_info) {
+            let Frame::Multiple { file_idx, .. } = frame else {
+                continue;
+            };
+            if *file_idx == 0 || *file_idx > data.strings.len() {
+                continue;
+            }
+
+            let fn_name = fn_name_from_frame(&data.strings, Some(frame));
+            file_by_function
+                .entry(fn_name.to_string())
+                .or_insert_with(|| data.strings[file_idx - 1].clone());
+        }
+    }
+
+    file_by_function
+}
+
+fn show_frame_tooltip(
+    ui: &mut Ui,
+    info: &MemInfo,
+    tree: &CallTree,
+    source: Source,
+    hovered: &HoveredFrame,
+    format_value: impl Fn(f64) -> String,
+) {
+    match hovered.label.strip_suffix(INLINED_SUFFIX) {
+        Some(name) => {
+            ui.label(RichText::new(name).strong().italics());
+            ui.label(RichText::new("inlined into its caller").weak());
+        }
+        None => {
+            ui.label(RichText::new(&hovered.label).strong());
+        }
+    }
+
+    if let Some(stack) = stack_of_frame(tree, source, hovered)
+        && let Some(frame) = stack.frames(&info.data).last()
+        && let Some((file_name, line_number)) = frame_file(&info.data, frame)
+    {
+        ui.label(format!("{file_name}:{line_number}"));
+    }
+
+    ui.separator();
+
+    Grid::new("frame_tooltip").num_columns(2).show(ui, |ui| {
+        if let Source::Memory(_) = source {
+            let metrics = tree.node(hovered.id).cost;
+
+            ui.label("Peak:");
+            ui.label(ByteSize::b(metrics.peaked).to_string());
+            ui.end_row();
+
+            ui.label("Leaked:");
+            ui.label(ByteSize::b(metrics.leaked).to_string());
+            ui.end_row();
+
+            ui.label("Allocations:");
+            ui.label(metrics.allocations.to_string());
+            ui.end_row();
+
+            ui.label("Temporary:");
+            ui.label(metrics.temporary.to_string());
+            ui.end_row();
+        } else {
+            ui.label("Value:");
+            ui.label(format_value(hovered.value));
+            ui.end_row();
+        }
+
+        ui.label("Of root:");
+        ui.label(format!(
+            "{:.2}%",
+            percent(hovered.value, hovered.root_value)
+        ));
+        ui.end_row();
+
+        ui.label("Of parent:");
+        ui.label(format!(
+            "{:.2}%",
+            percent(hovered.value, hovered.parent_value)
+        ));
+        ui.end_row();
+    });
+}
+
+/// Label of a frame in the memory flamegraphs, the function name marked when inlined.
+fn frame_label(data: &AccumulatedData, frame: &Frame, inlined: bool) -> String {
+    let name = fn_name_from_frame(&data.strings, Some(frame));
+    if inlined {
+        format!("{name}{INLINED_SUFFIX}")
+    } else {
+        name.to_string()
     }
 }
 
-fn get_frames_from_ip_info(data: &AccumulatedData, ip_info: &InstructionPointer) -> Vec<String> {
-    // TODO: check the order of ip_info.inlined
-    ip_info
-        .frame
-        .as_ref()
-        .into_iter()
-        .chain(&ip_info.inlined)
-        .map(|frame| fn_name_from_frame(&data.strings, Some(frame)).to_string())
-        .collect()
+fn percent(value: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        value / total * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Call stack of a frame of the memory flamegraphs, whose nodes are those of the call tree.
+fn stack_of_frame(tree: &CallTree, source: Source, frame: &HoveredFrame) -> Option<StackRef> {
+    let Source::Memory(_) = source else {
+        return None;
+    };
+    (frame.id != ROOT).then(|| tree.stack(frame.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process;
+
+    /// Writes `content` to a file of the temp directory removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("memtrace-ui-{}-{name}", process::id()));
+            fs::write(&path, content).expect("failed to write temp file");
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn read(name: &str, content: &str) -> io::Result<FoldedFile> {
+        FoldedFile::read(&TempFile::new(name, content).0)
+    }
+
+    #[test]
+    fn read_folded_file() {
+        let file = read(
+            "valid.folded",
+            "# comment\nmain;run 10\n\n  main;idle 2.5  \n",
+        )
+        .expect("valid folded file");
+        assert!(file.name.ends_with("valid.folded"));
+        assert_eq!(file.lines, ["main;run 10", "main;idle 2.5"]);
+    }
+
+    #[test]
+    fn read_rejects_malformed_lines() {
+        for (name, content) in [
+            ("no-value.folded", "main;run 10\nmain;run\n"),
+            ("bad-value.folded", "main;run 10\nmain;run ten\n"),
+            ("no-frames.folded", "main;run 10\n 10\n"),
+            ("infinite.folded", "main;run 10\nmain;run inf\n"),
+        ] {
+            let err = read(name, content).err().expect(name);
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{name}");
+            assert!(err.to_string().ends_with("line 2"), "{name}: {err}");
+        }
+    }
 }
@@ -0,0 +1,207 @@
+use crate::ui::events::{Event, EventKind, Events};
+use memtrace_utils::parser::AccumulatedData;
+use std::collections::HashMap;
+
+/// Sites are reported once one of their buffers grew at least this many times.
+const MIN_REALLOCATIONS: u64 = 2;
+
+/// A call site reallocating the same buffers to growing sizes, such as `Vec::push` on a vector
+/// created without capacity.
+pub struct GrowthSite {
+    /// Trace of the reallocations.
+    pub trace_idx: u64,
+    pub buffers: u64,
+    pub reallocations: u64,
+    /// Bytes of the old blocks, which are copied when a reallocation moves the buffer.
+    pub copied: u64,
+    pub max_final_size: u64,
+    pub total_final_size: u64,
+    /// Most reallocations of a single buffer.
+    pub longest_chain: u64,
+}
+
+impl GrowthSite {
+    pub fn average_final_size(&self) -> u64 {
+        self.total_final_size.checked_div(self.buffers).unwrap_or(0)
+    }
+}
+
+/// Buffer being grown by successive reallocations.
+struct Chain {
+    trace_idx: u64,
+    reallocations: u64,
+    copied: u64,
+    size: u64,
+}
+
+/// Finds reallocations as a free followed by the allocation of a larger block from the same
+/// trace, as the trace records a reallocation as both, and follows each buffer through its
+/// successive reallocations.
+pub fn find_growing_buffers(data: &AccumulatedData, events: &Events) -> Vec<GrowthSite> {
+    let mut chains: Vec<Chain> = Vec::new();
+    // chains whose current block has the allocation info of the key
+    let mut open: HashMap<u32, Vec<usize>> = HashMap::new();
+
+    let mut events = events.iter().peekable();
+    while let Some(event) = events.next() {
+        let Some((freed, allocated, trace_idx)) = events
+            .peek()
+            .and_then(|next| reallocation(data, event, *next))
+        else {
+            // a buffer freed for good ends its chain
+            if event.kind == EventKind::Free
+                && let Some(ids) = open.get_mut(&event.info_idx)
+            {
+                ids.pop();
+            }
+            continue;
+        };
+        events.next();
+
+        let existing = open.get_mut(&freed).and_then(|ids| {
+            let idx = ids
+                .iter()
+                .rposition(|id| chains[*id].trace_idx == trace_idx)?;
+            Some(ids.remove(idx))
+        });
+        let id = existing.unwrap_or_else(|| {
+            chains.push(Chain {
+                trace_idx,
+                reallocations: 0,
+                copied: 0,
+                size: 0,
+            });
+            chains.len() - 1
+        });
+
+        let chain = &mut chains[id];
+        chain.reallocations += 1;
+        chain.copied += data.allocation_infos[freed as usize].size;
+        chain.size = data.allocation_infos[allocated as usize].size;
+        open.entry(allocated).or_default().push(id);
+    }
+
+    let mut sites: HashMap<u64, GrowthSite> = HashMap::new();
+    for chain in chains {
+        let site = sites.entry(chain.trace_idx).or_insert(GrowthSite {
+            trace_idx: chain.trace_idx,
+            buffers: 0,
+            reallocations: 0,
+            copied: 0,
+            max_final_size: 0,
+            total_final_size: 0,
+            longest_chain: 0,
+        });
+        site.buffers += 1;
+        site.reallocations += chain.reallocations;
+        site.copied += chain.copied;
+        site.max_final_size = site.max_final_size.max(chain.size);
+        site.total_final_size += chain.size;
+        site.longest_chain = site.longest_chain.max(chain.reallocations);
+    }
+
+    let mut sites = sites
+        .into_values()
+        .filter(|site| site.longest_chain >= MIN_REALLOCATIONS)
+        .collect::<Vec<_>>();
+    sites.sort_by(|a, b| b.copied.cmp(&a.copied).then(a.trace_idx.cmp(&b.trace_idx)));
+    sites
+}
+
+/// The freed and allocated infos and the trace of a reallocation made of two events. An
+/// allocation followed by a free is not one: that is how `x = grow(&x)` replaces a value.
+fn reallocation(data: &AccumulatedData, first: Event, second: Event) -> Option<(u32, u32, u64)> {
+    if first.kind != EventKind::Free || second.kind != EventKind::Alloc {
+        return None;
+    }
+    let (freed, allocated) = (first.info_idx, second.info_idx);
+
+    let old = data.allocation_infos.get(freed as usize)?;
+    let new = data.allocation_infos.get(allocated as usize)?;
+    if new.size <= old.size {
+        return None;
+    }
+
+    let old_trace = data.allocations.get(old.allocation_idx as usize)?.trace_idx;
+    let trace_idx = data.allocations.get(new.allocation_idx as usize)?.trace_idx;
+    (old_trace == trace_idx).then_some((freed, allocated, trace_idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memtrace_utils::parser::{Allocation, AllocationInfo};
+
+    /// Data of allocations from traces 1, 2 and 3, and `infos` as allocation index and size.
+    fn data(infos: &[(u64, u64)]) -> AccumulatedData {
+        let mut data = AccumulatedData::new();
+        data.allocations = (1..=3).map(Allocation::new).collect();
+        data.allocation_infos = infos
+            .iter()
+            .map(|(allocation_idx, size)| AllocationInfo::new(*allocation_idx, *size))
+            .collect();
+        data
+    }
+
+    fn growing_buffers(infos: &[(u64, u64)], trace: &str) -> Vec<GrowthSite> {
+        let events = Events::parse(trace.as_bytes()).expect("valid trace");
+        find_growing_buffers(&data(infos), &events)
+    }
+
+    /// Blocks of 8, 16 and 32 bytes from trace 1, then 4 and 64 bytes from traces 2 and 3.
+    const INFOS: [(u64, u64); 5] = [(0, 8), (0, 16), (0, 32), (1, 4), (2, 64)];
+
+    #[test]
+    fn follows_a_buffer_through_its_reallocations() {
+        let sites = growing_buffers(&INFOS, "+ 0\n- 0\n+ 1\n- 1\n+ 2\n- 2\n");
+
+        let [site] = sites.as_slice() else {
+            panic!("expected one site, got {}", sites.len());
+        };
+        assert_eq!(site.trace_idx, 1);
+        assert_eq!(site.buffers, 1);
+        assert_eq!(site.reallocations, 2);
+        assert_eq!(site.copied, 8 + 16);
+        assert_eq!(site.max_final_size, 32);
+        assert_eq!(site.longest_chain, 2);
+    }
+
+    #[test]
+    fn ignores_unrelated_free_and_allocation() {
+        // a block of trace 2 freed right before a larger block of trace 3 is allocated, between
+        // the reallocations of trace 1
+        let trace = "+ 0\n+ 3\n- 0\n+ 1\n- 3\n+ 4\n- 1\n+ 2\n";
+        let sites = growing_buffers(&INFOS, trace);
+
+        let [site] = sites.as_slice() else {
+            panic!("expected one site, got {}", sites.len());
+        };
+        assert_eq!(site.trace_idx, 1);
+        assert_eq!(site.reallocations, 2);
+        assert_eq!(site.copied, 8 + 16);
+    }
+
+    #[test]
+    fn shrinking_is_not_growth() {
+        assert!(growing_buffers(&INFOS, "+ 2\n- 2\n+ 1\n- 1\n+ 0\n").is_empty());
+    }
+
+    #[test]
+    fn reassignment_is_not_reallocation() {
+        // `x = make_bigger(&x)` in a loop allocates the new value before dropping the old one
+        assert!(growing_buffers(&INFOS, "+ 0\n+ 1\n- 0\n+ 2\n- 1\n").is_empty());
+    }
+
+    #[test]
+    fn free_and_allocation_from_different_traces_are_not_a_reallocation() {
+        // a block of trace 2 freed right before a larger block of trace 3 is allocated
+        assert!(growing_buffers(&INFOS, "+ 3\n- 3\n+ 4\n- 4\n+ 3\n- 3\n+ 4\n").is_empty());
+    }
+
+    #[test]
+    fn freeing_a_buffer_ends_its_chain() {
+        // two buffers reallocated once each are not one buffer reallocated twice
+        let sites = growing_buffers(&INFOS, "+ 0\n- 0\n+ 1\n- 1\n+ 0\n- 0\n+ 1\n");
+        assert!(sites.is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::ui::MemInfo;
+use crate::ui::events::Events;
 use egui::RichText;
 use egui::Ui;
 
@@ -7,3 +9,21 @@ pub fn add_key_value(ui: &mut Ui, key: &str, value: impl ToString) {
         ui.label(value.to_string());
     });
 }
+
+/// Allocation events of the trace, or `None` after telling why there are none.
+pub fn show_events<'a>(ui: &mut Ui, info: &'a MemInfo) -> Option<&'a Events> {
+    match info.events() {
+        Ok(events) if !events.is_empty() => Some(events),
+        Ok(_) => {
+            ui.label("The trace contains no allocation events.");
+            None
+        }
+        Err(err) => {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("Failed to read allocation events: {err}"),
+            );
+            None
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use crate::ui::MemInfo;
+use crate::ui::events::Events;
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::stack::{
+    Navigation, StackRef, frame_file, owner_frame, stack_menu, trace_frames, trace_inline_frames,
+};
+use crate::ui::suppressions::{Pattern, Suppressions};
+use crate::ui::symbols::{self, SymbolOptions};
+use bytesize::ByteSize;
+use egui::{Align, CollapsingHeader, Layout, RichText, ScrollArea, Sense, Ui, vec2};
+use egui_extras::{Column, TableBuilder};
+use memtrace_utils::parser::AccumulatedData;
+
+/// Options matching the symbols against the suppressions, which keep the generic arguments.
+const MATCH_OPTIONS: SymbolOptions = SymbolOptions {
+    demangle: true,
+    strip_hashes: true,
+    collapse_generics: false,
+    trim_closures: false,
+};
+
+struct LeakSite {
+    trace_idx: u64,
+    blocks: u64,
+    bytes: u64,
+    /// Index of the first active pattern matching the stack.
+    suppressed_by: Option<usize>,
+}
+
+/// Allocations never freed by the end of the trace, by call stack, without the ones matching
+/// the suppressions.
+pub struct LeaksPage {
+    suppressions: Suppressions,
+    use_defaults: bool,
+    show_suppressed: bool,
+    path: String,
+    status: Option<String>,
+    leaks: Vec<LeakSite>,
+    computed: bool,
+    dirty: bool,
+    /// Indices into `leaks` of the rows shown.
+    rows: Vec<usize>,
+    selected: Option<usize>,
+}
+
+impl LeaksPage {
+    pub fn new(suppressions: Suppressions) -> Self {
+        Self {
+            suppressions,
+            use_defaults: true,
+            show_suppressed: false,
+            path: String::new(),
+            status: None,
+            leaks: Vec::new(),
+            computed: false,
+            dirty: true,
+            rows: Vec::new(),
+            selected: None,
+        }
+    }
+
+    /// Patterns of the suppressions file followed by the default ones when enabled.
+    fn patterns(&self) -> Vec<Pattern> {
+        let mut patterns = self.suppressions.patterns.clone();
+        if self.use_defaults {
+            patterns.extend(Suppressions::defaults().patterns);
+        }
+        patterns
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        if !self.computed {
+            let empty = Events::default();
+            let events = info.events().unwrap_or_else(|err| {
+                self.status = Some(format!("Failed to read allocation events: {err}"));
+                &empty
+            });
+            self.leaks = find_leaks(&info.data, events);
+            self.computed = true;
+            self.dirty = true;
+        }
+
+        let patterns = self.patterns();
+        if self.dirty {
+            self.update_rows(&info.data, &patterns);
+        }
+
+        self.show_controls(ui, &patterns);
+        ui.separator();
+
+        let mut navigation = None;
+        let available = ui.available_size();
+        ui.horizontal(|ui| {
+            ui.allocate_ui(vec2(available.x * 0.6, available.y), |ui| {
+                navigation = self.show_table(ui, info, symbol_options, &patterns);
+            });
+            ui.separator();
+            ui.allocate_ui(ui.available_size(), |ui| {
+                if let Some(nav) = self.show_stack(ui, info, symbol_options) {
+                    navigation = Some(nav);
+                }
+            });
+        });
+
+        navigation
+    }
+
+    fn show_controls(&mut self, ui: &mut Ui, patterns: &[Pattern]) {
+        ui.horizontal(|ui| {
+            ui.label("Suppressions file:");
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Load").clicked() {
+                match Suppressions::read(&self.path) {
+                    Ok(suppressions) => {
+                        self.status = Some(format!(
+                            "Loaded {} patterns from {}",
+                            suppressions.patterns.len(),
+                            self.path
+                        ));
+                        self.suppressions = suppressions;
+                    }
+                    Err(err) => self.status = Some(format!("Failed to read {}: {err}", self.path)),
+                }
+                self.dirty = true;
+            }
+            if !self.suppressions.patterns.is_empty() && ui.button("Clear").clicked() {
+                self.suppressions = Suppressions::default();
+                self.status = None;
+                self.dirty = true;
+            }
+            if let Some(status) = &self.status {
+                ui.label(RichText::new(status).weak());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.use_defaults, "Hide common intentional leaks")
+                .on_hover_text(
+                    "Globals initialised once, such as lazy_static, OnceLock or thread locals",
+                )
+                .changed()
+            {
+                self.dirty = true;
+            }
+            if ui
+                .checkbox(&mut self.show_suppressed, "Show suppressed")
+                .changed()
+            {
+                self.dirty = true;
+            }
+        });
+
+        let (mut shown, mut suppressed) = ((0, 0, 0), (0, 0, 0));
+        let mut matches = vec![0; patterns.len()];
+        for leak in &self.leaks {
+            let total = match leak.suppressed_by {
+                Some(idx) => {
+                    matches[idx] += 1;
+                    &mut suppressed
+                }
+                None => &mut shown,
+            };
+            total.0 += 1;
+            total.1 += leak.blocks;
+            total.2 += leak.bytes;
+        }
+
+        ui.label(format!(
+            "{} leaked sites, {} blocks, {}; {} sites suppressed, {} blocks, {}",
+            shown.0,
+            shown.1,
+            ByteSize::b(shown.2),
+            suppressed.0,
+            suppressed.1,
+            ByteSize::b(suppressed.2),
+        ));
+
+        CollapsingHeader::new(format!("Suppression patterns ({})", patterns.len()))
+            .id_salt("suppression_patterns")
+            .show(ui, |ui| {
+                for (pattern, count) in patterns.iter().zip(matches) {
+                    ui.label(format!("{pattern}  ({count} sites)"));
+                }
+            });
+    }
+
+    fn update_rows(&mut self, data: &AccumulatedData, patterns: &[Pattern]) {
+        let suppressions = Suppressions {
+            patterns: patterns.to_vec(),
+        };
+        for leak in &mut self.leaks {
+            let functions = trace_frames(data, leak.trace_idx)
+                .map(|frame| {
+                    let name = fn_name_from_frame(&data.strings, Some(frame));
+                    symbols::prettify(name, MATCH_OPTIONS)
+                })
+                .collect::<Vec<_>>();
+            let functions = functions
+                .iter()
+                .rev()
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>();
+            leak.suppressed_by = suppressions.find(&functions);
+        }
+
+        self.rows = (0..self.leaks.len())
+            .filter(|idx| self.show_suppressed || self.leaks[*idx].suppressed_by.is_none())
+            .collect();
+        if self.selected.is_some_and(|idx| !self.rows.contains(&idx)) {
+            self.selected = None;
+        }
+        self.dirty = false;
+    }
+
+    fn show_table(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+        patterns: &[Pattern],
+    ) -> Option<Navigation> {
+        const HEIGHT: f32 = 20.0;
+
+        let mut navigation = None;
+        let mut clicked = None;
+        TableBuilder::new(ui)
+            .id_salt("leaks_table")
+            .striped(true)
+            .resizable(true)
+            .auto_shrink(false)
+            .sense(Sense::click())
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::remainder().at_least(200.0).clip(true))
+            .columns(Column::auto().at_least(70.0), 2)
+            .header(HEIGHT, |mut header| {
+                for label in ["Location", "Blocks", "Bytes"] {
+                    header.col(|ui| {
+                        ui.strong(label);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(HEIGHT, self.rows.len(), |mut row| {
+                    let idx = self.rows[row.index()];
+                    let leak = &self.leaks[idx];
+                    let stack = StackRef::full(&info.data, leak.trace_idx);
+                    row.set_selected(self.selected == Some(idx));
+
+                    row.col(|ui| {
+                        let frame = owner_frame(&info.data, leak.trace_idx);
+                        let name = fn_name_from_frame(&info.data.strings, frame);
+                        let mut text = RichText::new(symbols::prettify(name, symbol_options));
+                        if leak.suppressed_by.is_some() {
+                            text = text.weak().strikethrough();
+                        }
+                        let response = ui.label(text);
+                        if let Some(pattern) = leak.suppressed_by {
+                            response.on_hover_text(format!("suppressed by {}", patterns[pattern]));
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(leak.blocks.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(leak.bytes).to_string());
+                    });
+
+                    let response = row.response();
+                    if response.clicked() {
+                        clicked = Some(idx);
+                    }
+                    if response.double_clicked() {
+                        navigation = Some(Navigation::Details(stack));
+                    }
+                    response.context_menu(|ui| {
+                        if let Some(nav) = stack_menu(ui, &info.data, stack) {
+                            navigation = Some(nav);
+                        }
+                    });
+                });
+            });
+
+        if clicked.is_some() {
+            self.selected = clicked;
+        }
+        navigation
+    }
+
+    /// Full call stack of the selected leak, innermost frame first.
+    fn show_stack(
+        &self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        let Some(leak) = self.selected.map(|idx| &self.leaks[idx]) else {
+            ui.label(RichText::new("Select a leak to see its call stack.").weak());
+            return None;
+        };
+        let data = &info.data;
+        let stack = StackRef::full(data, leak.trace_idx);
+
+        let mut navigation = None;
+        ui.horizontal(|ui| {
+            ui.strong(format!(
+                "{} blocks, {}",
+                leak.blocks,
+                ByteSize::b(leak.bytes)
+            ));
+            if ui.small_button("Copy stack").clicked() {
+                ui.ctx().copy_text(stack.to_text(data));
+            }
+        });
+        ui.separator();
+
+        ScrollArea::vertical()
+            .id_salt("leak_stack")
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for (frame, inlined) in trace_inline_frames(data, leak.trace_idx) {
+                    let name = fn_name_from_frame(&data.strings, Some(frame));
+                    let text = RichText::new(symbols::prettify(name, symbol_options).as_ref());
+                    if inlined {
+                        ui.horizontal(|ui| {
+                            ui.label(text.italics());
+                            ui.label(RichText::new("inlined").small().weak());
+                        });
+                    } else {
+                        ui.label(text);
+                    }
+                    if let Some((file_name, line_number)) = frame_file(data, frame)
+                        && ui
+                            .link(RichText::new(format!("    at {file_name}:{line_number}")).weak())
+                            .clicked()
+                    {
+                        navigation = Some(Navigation::OpenInEditor {
+                            file_name: file_name.to_string(),
+                            line_number,
+                        });
+                    }
+                }
+            });
+
+        navigation
+    }
+}
+
+/// Call stacks with allocations still alive at the end of the trace, the largest first.
+fn find_leaks(data: &AccumulatedData, events: &Events) -> Vec<LeakSite> {
+    let live = events.live_counts(data.allocation_infos.len());
+
+    let mut blocks = vec![0; data.allocations.len()];
+    for (alloc_info, count) in data.allocation_infos.iter().zip(live) {
+        if let Some(blocks) = blocks.get_mut(alloc_info.allocation_idx as usize) {
+            *blocks += count;
+        }
+    }
+
+    let mut leaks = data
+        .allocations
+        .iter()
+        .zip(blocks)
+        .filter(|(allocation, _)| allocation.data.leaked > 0)
+        .map(|(allocation, blocks)| LeakSite {
+            trace_idx: allocation.trace_idx,
+            blocks,
+            bytes: allocation.data.leaked,
+            suppressed_by: None,
+        })
+        .collect::<Vec<_>>();
+    leaks.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.trace_idx.cmp(&b.trace_idx)));
+    leaks
+}
@@ -0,0 +1,431 @@
+use crate::ui::MemInfo;
+use crate::ui::events::{EventKind, Events};
+use crate::ui::helpers::show_events;
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::stack::{Navigation, StackRef, stack_menu, trace_frames};
+use crate::ui::symbols::{self, SymbolOptions};
+use crate::ui::widgets::histogram::{Bar, Histogram};
+use bytesize::ByteSize;
+use egui::{Align, Layout, RichText, Sense, Ui, vec2};
+use egui_extras::{Column, TableBuilder};
+use memtrace_utils::parser::AccumulatedData;
+use std::time::Duration;
+
+const HISTOGRAM_HEIGHT: f32 = 160.0;
+
+/// Upper bounds in milliseconds of the lifetimes of the freed blocks, followed by a bucket of
+/// the leaked ones.
+const BUCKET_LIMITS: [u64; 6] = [1, 10, 100, 1_000, 10_000, u64::MAX];
+const BUCKET_LABELS: [&str; 7] = ["<1ms", "<10ms", "<100ms", "<1s", "<10s", "10s+", "leaked"];
+const LEAKED: usize = BUCKET_LIMITS.len();
+
+/// Sites freeing most of their blocks within this many milliseconds are short-lived.
+const SHORT_LIFETIME: u64 = 10;
+/// Short-lived sites are reported when their blocks are this large on average.
+const LARGE_SIZE: u64 = 64 * 1024;
+/// Sites whose freed blocks mostly live at least this many milliseconds are long-lived.
+const LONG_LIFETIME: u64 = 1_000;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Report {
+    AllSites,
+    ShortLivedLarge,
+    LongLivedTemporary,
+}
+
+impl Report {
+    fn label(self) -> &'static str {
+        match self {
+            Report::AllSites => "All sites",
+            Report::ShortLivedLarge => "Short-lived but large",
+            Report::LongLivedTemporary => "Long-lived temporaries",
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Report::AllSites => {
+                "Every allocation site, the ones allocating most often first.".to_string()
+            }
+            Report::ShortLivedLarge => format!(
+                "Sites freeing most blocks within {SHORT_LIFETIME} ms while allocating {} or more \
+                 per block, where reusing a buffer avoids the allocations.",
+                ByteSize::b(LARGE_SIZE)
+            ),
+            Report::LongLivedTemporary => format!(
+                "Sites freeing their blocks, but mostly after {} or more, by bytes held over time.",
+                format_lifetime(LONG_LIFETIME as f64)
+            ),
+        }
+    }
+}
+
+/// Lifetimes of the blocks allocated by one trace.
+struct SiteLifetimes {
+    trace_idx: u64,
+    allocations: u64,
+    freed: u64,
+    bytes: u64,
+    /// Blocks in each of the `BUCKET_LABELS` buckets.
+    distribution: [u64; BUCKET_LABELS.len()],
+    /// Sum of the lifetimes of the freed blocks, in milliseconds.
+    total_lifetime: u64,
+    max_lifetime: u64,
+    /// Bytes multiplied by the milliseconds they were held for, summed over the freed blocks.
+    held: f64,
+}
+
+impl SiteLifetimes {
+    fn average_lifetime(&self) -> f64 {
+        if self.freed > 0 {
+            self.total_lifetime as f64 / self.freed as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn average_size(&self) -> u64 {
+        self.bytes.checked_div(self.allocations).unwrap_or(0)
+    }
+
+    /// Freed blocks living less than `limit` milliseconds.
+    fn freed_within(&self, limit: u64) -> u64 {
+        BUCKET_LIMITS
+            .iter()
+            .zip(&self.distribution)
+            .take_while(|(bound, _)| **bound <= limit)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// How long allocations live, from the time of each allocation to the time of its free.
+pub struct LifetimesPage {
+    report: Report,
+    sites: Vec<SiteLifetimes>,
+    computed: bool,
+    /// Bucket of the histogram the sites are filtered by.
+    selected: Option<usize>,
+    /// Indices into `sites` shown by the current report.
+    rows: Vec<usize>,
+    dirty: bool,
+}
+
+impl LifetimesPage {
+    pub fn new() -> Self {
+        Self {
+            report: Report::AllSites,
+            sites: Vec::new(),
+            computed: false,
+            selected: None,
+            rows: Vec::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        let events = show_events(ui, info)?;
+
+        if !self.computed {
+            self.sites = site_lifetimes(&info.data, events);
+            self.computed = true;
+            self.dirty = true;
+        }
+
+        let mut distribution = [0; BUCKET_LABELS.len()];
+        for site in &self.sites {
+            for (total, count) in distribution.iter_mut().zip(site.distribution) {
+                *total += count;
+            }
+        }
+        let bars = BUCKET_LABELS
+            .iter()
+            .zip(distribution)
+            .map(|(label, count)| Bar {
+                label: label.to_string(),
+                value: count as f64,
+            })
+            .collect::<Vec<_>>();
+
+        ui.label(
+            RichText::new(
+                "Lifetimes are measured between the timestamps of the trace, frees are paired \
+                 with the latest allocation of the same size and call stack.",
+            )
+            .weak(),
+        );
+        let response = Histogram::new(&bars, self.selected, HISTOGRAM_HEIGHT).show(ui);
+        if let Some(idx) = response.hovered {
+            response.response.on_hover_ui_at_pointer(|ui| {
+                ui.label(RichText::new(BUCKET_LABELS[idx]).strong());
+                ui.label(format!("blocks: {}", distribution[idx]));
+            });
+        }
+        if let Some(idx) = response.clicked {
+            self.selected = if self.selected == Some(idx) {
+                None
+            } else {
+                Some(idx)
+            };
+            self.report = Report::AllSites;
+            self.dirty = true;
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            for report in [
+                Report::AllSites,
+                Report::ShortLivedLarge,
+                Report::LongLivedTemporary,
+            ] {
+                if ui
+                    .selectable_label(self.report == report, report.label())
+                    .clicked()
+                {
+                    self.report = report;
+                    self.dirty = true;
+                }
+            }
+            if self.report == Report::AllSites
+                && let Some(idx) = self.selected
+            {
+                ui.label(format!("with blocks in {}", BUCKET_LABELS[idx]));
+                if ui.small_button("✖").clicked() {
+                    self.selected = None;
+                    self.dirty = true;
+                }
+            }
+        });
+        ui.label(RichText::new(self.report.description()).weak());
+
+        if self.dirty {
+            self.update_rows();
+        }
+
+        self.show_sites(ui, info, symbol_options)
+    }
+
+    fn update_rows(&mut self) {
+        let sites = &self.sites;
+        let mut rows = (0..sites.len()).collect::<Vec<_>>();
+
+        match self.report {
+            Report::AllSites => match self.selected {
+                Some(bucket) => {
+                    rows.retain(|idx| sites[*idx].distribution[bucket] > 0);
+                    rows.sort_by_key(|idx| std::cmp::Reverse(sites[*idx].distribution[bucket]));
+                }
+                None => rows.sort_by_key(|idx| std::cmp::Reverse(sites[*idx].allocations)),
+            },
+            Report::ShortLivedLarge => {
+                rows.retain(|idx| {
+                    let site = &sites[*idx];
+                    site.average_size() >= LARGE_SIZE
+                        && site.freed_within(SHORT_LIFETIME) * 2 > site.allocations
+                });
+                rows.sort_by_key(|idx| std::cmp::Reverse(sites[*idx].bytes));
+            }
+            Report::LongLivedTemporary => {
+                rows.retain(|idx| {
+                    let site = &sites[*idx];
+                    site.freed > 0
+                        && site.distribution[LEAKED] == 0
+                        && (site.freed - site.freed_within(LONG_LIFETIME)) * 2 > site.freed
+                });
+                rows.sort_by(|a, b| sites[*b].held.total_cmp(&sites[*a].held));
+            }
+        }
+
+        self.rows = rows;
+        self.dirty = false;
+    }
+
+    fn show_sites(
+        &self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        const HEIGHT: f32 = 20.0;
+
+        let mut navigation = None;
+        TableBuilder::new(ui)
+            .id_salt("lifetime_sites")
+            .striped(true)
+            .resizable(true)
+            .auto_shrink(false)
+            .sense(Sense::click())
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::remainder().at_least(200.0).clip(true))
+            .columns(Column::auto().at_least(80.0), 5)
+            .column(Column::auto().at_least(120.0))
+            .header(HEIGHT, |mut header| {
+                for label in [
+                    "Function",
+                    "Allocations",
+                    "Bytes",
+                    "Avg size",
+                    "Avg lifetime",
+                    "Max lifetime",
+                    "Distribution",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(label);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(HEIGHT, self.rows.len(), |mut row| {
+                    let site = &self.sites[self.rows[row.index()]];
+                    let stack = StackRef::full(&info.data, site.trace_idx);
+
+                    row.col(|ui| {
+                        let frame = trace_frames(&info.data, site.trace_idx).next();
+                        let name = fn_name_from_frame(&info.data.strings, frame);
+                        ui.label(symbols::prettify(name, symbol_options).as_ref())
+                            .on_hover_ui(|ui| {
+                                ui.label(stack.to_text(&info.data));
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.label(site.allocations.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(site.bytes).to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(site.average_size()).to_string());
+                    });
+                    row.col(|ui| {
+                        if site.freed > 0 {
+                            ui.label(format_lifetime(site.average_lifetime()));
+                        }
+                    });
+                    row.col(|ui| {
+                        if site.freed > 0 {
+                            ui.label(format_lifetime(site.max_lifetime as f64));
+                        }
+                    });
+                    row.col(|ui| {
+                        show_distribution(ui, &site.distribution);
+                    });
+
+                    let response = row.response();
+                    if response.double_clicked() {
+                        navigation = Some(Navigation::Details(stack));
+                    }
+                    response.context_menu(|ui| {
+                        if let Some(nav) = stack_menu(ui, &info.data, stack) {
+                            navigation = Some(nav);
+                        }
+                    });
+                });
+            });
+
+        navigation
+    }
+}
+
+/// Tiny bar chart of the lifetime buckets of a site.
+fn show_distribution(ui: &mut Ui, distribution: &[u64; BUCKET_LABELS.len()]) {
+    const BAR_WIDTH: f32 = 12.0;
+
+    let size = vec2(
+        BAR_WIDTH * distribution.len() as f32,
+        ui.available_height() - 4.0,
+    );
+    let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let max = distribution.iter().copied().max().unwrap_or(0).max(1);
+
+    for (idx, count) in distribution.iter().enumerate() {
+        let height = *count as f32 / max as f32 * rect.height();
+        let x = rect.min.x + idx as f32 * BAR_WIDTH;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x + 1.0, rect.max.y - height),
+            egui::pos2(x + BAR_WIDTH - 1.0, rect.max.y),
+        );
+        let color = if idx == LEAKED {
+            ui.visuals().warn_fg_color
+        } else {
+            ui.visuals().selection.bg_fill
+        };
+        painter.rect_filled(bar, 1.0, color);
+    }
+
+    response.on_hover_ui(|ui| {
+        for (label, count) in BUCKET_LABELS.iter().zip(distribution) {
+            ui.label(format!("{label}: {count}"));
+        }
+    });
+}
+
+fn format_lifetime(ms: f64) -> String {
+    format!("{:.1?}", Duration::from_secs_f64(ms / 1000.0))
+}
+
+fn bucket_of(lifetime: u64) -> usize {
+    BUCKET_LIMITS
+        .iter()
+        .position(|limit| lifetime < *limit)
+        .unwrap_or(BUCKET_LIMITS.len() - 1)
+}
+
+/// Pairs every free with the latest allocation of the same allocation info still alive, as
+/// the trace does not record addresses, and sums the lifetimes by trace.
+fn site_lifetimes(data: &AccumulatedData, events: &Events) -> Vec<SiteLifetimes> {
+    let mut sites = data
+        .allocations
+        .iter()
+        .map(|allocation| SiteLifetimes {
+            trace_idx: allocation.trace_idx,
+            allocations: 0,
+            freed: 0,
+            bytes: 0,
+            distribution: [0; BUCKET_LABELS.len()],
+            total_lifetime: 0,
+            max_lifetime: 0,
+            held: 0.0,
+        })
+        .collect::<Vec<_>>();
+    let mut alive: Vec<Vec<u64>> = vec![Vec::new(); data.allocation_infos.len()];
+
+    for event in events.iter() {
+        let info_idx = event.info_idx as usize;
+        let Some(info) = data.allocation_infos.get(info_idx) else {
+            continue;
+        };
+        let site = &mut sites[info.allocation_idx as usize];
+
+        match event.kind {
+            EventKind::Alloc => {
+                site.allocations += 1;
+                site.bytes += info.size;
+                alive[info_idx].push(event.time);
+            }
+            EventKind::Free => {
+                let Some(time) = alive[info_idx].pop() else {
+                    continue;
+                };
+                let lifetime = event.time.saturating_sub(time);
+                site.freed += 1;
+                site.distribution[bucket_of(lifetime)] += 1;
+                site.total_lifetime += lifetime;
+                site.max_lifetime = site.max_lifetime.max(lifetime);
+                site.held += info.size as f64 * lifetime as f64;
+            }
+        }
+    }
+
+    for (info, blocks) in data.allocation_infos.iter().zip(&alive) {
+        sites[info.allocation_idx as usize].distribution[LEAKED] += blocks.len() as u64;
+    }
+
+    sites.retain(|site| site.allocations > 0);
+    sites
+}
@@ -1,16 +1,49 @@
+mod calltree;
+mod code;
+mod crates;
+mod details;
+mod editor;
+mod events;
 mod flamegraph;
+mod growth;
 mod helpers;
+mod leaks;
+mod lifetimes;
 mod overview;
+mod sizes;
+mod source_cache;
+mod sources;
+mod stack;
+mod suppressions;
+mod symbols;
 mod topdown;
+mod treemap;
 mod widgets;
 
-use crate::ui::flamegraph::FlamegraphPage;
+use crate::ui::calltree::CallTree;
+use crate::ui::crates::CratesPage;
+use crate::ui::details::{DetailsWindow, EventStats};
+pub use crate::ui::editor::EditorCommand;
+use crate::ui::events::Events;
+pub use crate::ui::flamegraph::{FlamegraphPage, FoldedFile, MemoryKind, write_folded};
+use crate::ui::leaks::LeaksPage;
+use crate::ui::lifetimes::LifetimesPage;
+use crate::ui::overview::OverviewPage;
+use crate::ui::sizes::SizesPage;
+pub use crate::ui::sources::SourceResolver;
+use crate::ui::stack::Navigation;
+pub use crate::ui::suppressions::Suppressions;
+use crate::ui::symbols::SymbolOptions;
 use crate::ui::topdown::TopDown;
+use crate::ui::treemap::TreemapPage;
 use eframe::emath::Align;
 use egui::Layout;
 use memtrace_utils::parser::AccumulatedData;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
-pub fn run_ui(data: MemInfo) -> eframe::Result {
+pub fn run_ui(data: MemInfo, folded: Option<FoldedFile>, settings: Settings) -> eframe::Result {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1024.0, 720.0]),
         ..Default::default()
@@ -18,7 +51,7 @@ pub fn run_ui(data: MemInfo) -> eframe::Result {
     eframe::run_native(
         "MemTrace",
         options,
-        Box::new(|_| Ok(Box::new(MemgraphApp::new(data)))),
+        Box::new(|_| Ok(Box::new(MemgraphApp::new(data, folded, settings)))),
     )
 }
 
@@ -27,30 +60,152 @@ enum MainTab {
     Overview,
     TopDown,
     Flamegraph,
+    Crates,
+    Treemap,
+    Sizes,
+    Lifetimes,
+    Leaks,
+}
+
+#[derive(Default)]
+pub struct Settings {
+    pub source_resolver: SourceResolver,
+    /// Command opening a source location, `None` when no editor is configured.
+    pub editor: Option<EditorCommand>,
+    pub symbols: SymbolOptions,
+    /// Merge recursive frames in the call tree and the flamegraph.
+    pub collapse_recursion: bool,
+    /// Leaks hidden from the Leaks tab.
+    pub suppressions: Suppressions,
 }
 
 pub struct MemInfo {
     pub app_name: String,
     pub data: AccumulatedData,
+    /// Trace the data was parsed from, `None` when showing a folded file.
+    trace_path: Option<PathBuf>,
+    events: OnceLock<io::Result<Events>>,
+    event_stats: OnceLock<EventStats>,
+}
+
+impl MemInfo {
+    pub fn new(app_name: String, data: AccumulatedData, trace_path: Option<PathBuf>) -> Self {
+        Self {
+            app_name,
+            data,
+            trace_path,
+            events: OnceLock::new(),
+            event_stats: OnceLock::new(),
+        }
+    }
+
+    pub fn has_trace(&self) -> bool {
+        self.trace_path.is_some()
+    }
+
+    /// Allocation events of the trace, read again from the trace the first time a page needs
+    /// them as most pages only show the totals of `data`.
+    pub fn events(&self) -> Result<&Events, &io::Error> {
+        self.events
+            .get_or_init(|| match &self.trace_path {
+                Some(path) => Events::read(path),
+                None => Ok(Events::default()),
+            })
+            .as_ref()
+    }
+
+    /// Allocation counts and live bytes of every allocation, computed from the events the first
+    /// time the details of a site are shown.
+    pub fn event_stats(&self) -> Result<&EventStats, &io::Error> {
+        let events = self.events()?;
+        Ok(self
+            .event_stats
+            .get_or_init(|| EventStats::new(&self.data, events)))
+    }
 }
 
 struct MemgraphApp {
-    info: MemInfo,
+    /// Shared with the threads building the details of a site.
+    info: Arc<MemInfo>,
     current_tab: MainTab,
+    overview: OverviewPage,
     fg_page: FlamegraphPage,
+    crates_page: CratesPage,
+    treemap_page: TreemapPage,
+    sizes_page: SizesPage,
+    lifetimes_page: LifetimesPage,
+    leaks_page: LeaksPage,
+    details: Option<DetailsWindow>,
+    call_tree: CallTree,
     top_down: TopDown,
+    settings: Settings,
+    status: Option<String>,
 }
 
 impl MemgraphApp {
-    pub fn new(info: MemInfo) -> Self {
-        let fg_page = FlamegraphPage::new(&info);
+    pub fn new(info: MemInfo, folded: Option<FoldedFile>, settings: Settings) -> Self {
+        let mut fg_page = FlamegraphPage::new(&info);
+        let call_tree = CallTree::new(&info.data, settings.collapse_recursion);
+
+        let current_tab = match folded {
+            Some(file) => {
+                fg_page.add_folded_file(file);
+                MainTab::Flamegraph
+            }
+            None => MainTab::Overview,
+        };
 
         Self {
             top_down: TopDown::new(&info),
-            info,
-            current_tab: MainTab::Overview,
+            call_tree,
+            info: Arc::new(info),
+            current_tab,
+            overview: OverviewPage::new(),
             fg_page,
+            crates_page: CratesPage::new(),
+            treemap_page: TreemapPage::new(),
+            sizes_page: SizesPage::new(),
+            lifetimes_page: LifetimesPage::new(),
+            leaks_page: LeaksPage::new(settings.suppressions.clone()),
+            details: None,
+            settings,
+            status: None,
+        }
+    }
+
+    fn open_in_editor(&mut self, file_name: &str, line_number: u32) {
+        let Some(editor) = &self.settings.editor else {
+            self.status = Some("No editor configured, pass --editor".to_string());
+            return;
+        };
+        let Some(path) = self.settings.source_resolver.resolve(file_name) else {
+            self.status = Some(format!("Source not found: {file_name}"));
+            return;
+        };
+
+        self.status = match editor.open(&path, line_number) {
+            Ok(()) => None,
+            Err(err) => Some(format!("Failed to run `{}`: {err}", editor.template())),
+        };
+    }
+
+    /// Window with the details of the selected allocation site, closed with its close button.
+    fn show_details(&mut self, ctx: &egui::Context) -> Option<Navigation> {
+        let details = self.details.as_mut()?;
+        let mut open = true;
+        let mut navigation = None;
+
+        egui::Window::new("Allocation site")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                navigation = details.show(ui, &self.info, self.settings.symbols);
+            });
+
+        if !open {
+            self.details = None;
         }
+        navigation
     }
 }
 
@@ -59,7 +214,16 @@ impl eframe::App for MemgraphApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
                 ui.horizontal(|ui| {
-                    for tab in [MainTab::Overview, MainTab::TopDown, MainTab::Flamegraph] {
+                    for tab in [
+                        MainTab::Overview,
+                        MainTab::TopDown,
+                        MainTab::Flamegraph,
+                        MainTab::Crates,
+                        MainTab::Treemap,
+                        MainTab::Sizes,
+                        MainTab::Lifetimes,
+                        MainTab::Leaks,
+                    ] {
                         let selected = self.current_tab == tab;
                         if ui
                             .selectable_label(selected, format!("{:?}", tab))
@@ -68,20 +232,83 @@ impl eframe::App for MemgraphApp {
                             self.current_tab = tab;
                         }
                     }
+
+                    ui.menu_button("Symbols", |ui| {
+                        let symbols = &mut self.settings.symbols;
+                        ui.checkbox(&mut symbols.demangle, "Demangle");
+                        ui.checkbox(&mut symbols.strip_hashes, "Strip hashes");
+                        ui.checkbox(&mut symbols.collapse_generics, "Collapse generics");
+                        ui.checkbox(&mut symbols.trim_closures, "Trim closures");
+                    });
+
+                    if ui
+                        .checkbox(&mut self.settings.collapse_recursion, "Collapse recursion")
+                        .changed()
+                    {
+                        self.call_tree =
+                            CallTree::new(&self.info.data, self.settings.collapse_recursion);
+                        self.top_down.reset();
+                        self.treemap_page.reset();
+                    }
+
+                    if let Some(status) = &self.status {
+                        ui.colored_label(ui.visuals().warn_fg_color, status);
+                        if ui.small_button("✖").clicked() {
+                            self.status = None;
+                        }
+                    }
                 });
 
                 ui.separator();
 
-                match self.current_tab {
-                    MainTab::Overview => {
-                        overview::show(ui, &self.info);
-                    }
+                let navigation = match self.current_tab {
+                    MainTab::Overview => self.overview.show(ui, &self.info, self.settings.symbols),
                     MainTab::TopDown => {
-                        self.top_down.show(ui);
+                        self.top_down
+                            .show(ui, &self.info, &self.call_tree, &mut self.settings)
                     }
                     MainTab::Flamegraph => {
-                        self.fg_page.show(ui);
+                        self.fg_page
+                            .show(ui, &self.info, &self.call_tree, &self.settings)
+                    }
+                    MainTab::Crates => {
+                        self.crates_page.show(ui, &self.info);
+                        None
+                    }
+                    MainTab::Treemap => {
+                        self.treemap_page
+                            .show(ui, &self.info, &self.call_tree, &self.settings)
+                    }
+                    MainTab::Sizes => self.sizes_page.show(ui, &self.info, self.settings.symbols),
+                    MainTab::Lifetimes => {
+                        self.lifetimes_page
+                            .show(ui, &self.info, self.settings.symbols)
+                    }
+                    MainTab::Leaks => self.leaks_page.show(ui, &self.info, self.settings.symbols),
+                };
+                // drawn on every frame, even when the page navigates
+                let details_navigation = self.show_details(ui.ctx());
+                let navigation = navigation.or(details_navigation);
+
+                match navigation {
+                    Some(Navigation::TopDown(stack)) => {
+                        self.top_down.reveal(&self.info, &self.call_tree, stack);
+                        self.current_tab = MainTab::TopDown;
+                    }
+                    Some(Navigation::Flamegraph(stack)) => {
+                        self.fg_page.focus(&self.info, &self.call_tree, stack);
+                        self.current_tab = MainTab::Flamegraph;
+                    }
+                    Some(Navigation::Details(stack)) => {
+                        self.details = Some(DetailsWindow::open(ui.ctx(), &self.info, stack));
+                    }
+                    Some(Navigation::OpenInEditor {
+                        file_name,
+                        line_number,
+                    }) => {
+                        self.open_in_editor(&file_name, line_number);
                     }
+                    None => {}
                 }
             });
         });
@@ -1,225 +1,660 @@
-use crate::ui::helpers::add_key_value;
 use crate::ui::MemInfo;
+use crate::ui::growth::{GrowthSite, find_growing_buffers};
+use crate::ui::helpers::add_key_value;
+use crate::ui::stack::{
+    Navigation, StackRef, frame_file, frame_location, owner_frame, stack_menu, trace_frames,
+};
+use crate::ui::symbols::{self, SymbolOptions};
 use bytesize::ByteSize;
 use eframe::emath::Align;
-use egui::{Layout, Ui};
+use egui::{ComboBox, DragValue, Layout, RichText, Sense, TextEdit, TopBottomPanel, Ui};
 use egui_extras::{Column, TableBuilder};
 use itertools::Itertools;
-use memtrace_utils::parser::{AccumulatedData, Frame};
-use std::time::Instant;
+use memtrace_utils::parser::{AccumulatedData, AllocationData, Frame};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
 
-pub fn show(ui: &mut Ui, info: &MemInfo) {
-    ui.with_layout(Layout::default(), |ui| {
-        ui.add_space(20.0);
-        ui.horizontal(|ui| {
-            ui.add_space(20.0);
-            ui.columns(3, |columns| {
-                let [col1, col2, col3] = columns.get_disjoint_mut([0, 1, 2]).unwrap();
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Grouping {
+    Function,
+    Line,
+    Stack,
+}
 
-                let total_ram = info.data.page_size * info.data.pages;
+impl Grouping {
+    fn label(self) -> &'static str {
+        match self {
+            Grouping::Function => "Function",
+            Grouping::Line => "File and line",
+            Grouping::Stack => "Call stack",
+        }
+    }
+}
 
-                add_key_value(col1, "application", &info.app_name);
-                add_key_value(col1, "total runtime", format!("{:?}", info.data.duration));
-                add_key_value(col1, "total system memory", ByteSize::b(total_ram));
+/// Allocation site an allocation is attributed to in the top tables.
+#[derive(PartialEq, Eq, Hash)]
+enum Site {
+    Function(usize),
+    Line(usize, u32),
+    Stack(u64),
+}
 
-                add_key_value(
-                    col2,
-                    "calls to allocation functions",
-                    info.data.total.allocations,
-                );
-                add_key_value(col2, "temporary allocations", info.data.total.temporary);
+/// One of the top tables, ranking allocation sites by a metric.
+struct TopTable {
+    label: &'static str,
+    header: &'static str,
+    metric: fn(&AllocationData) -> u64,
+    format: fn(u64) -> String,
+}
 
-                add_key_value(
-                    col3,
-                    "peak heap memory consumption",
-                    ByteSize::b(info.data.total.peak),
-                );
-                add_key_value(col3, "peak RSS", ByteSize::b(info.data.peak_rss));
-                add_key_value(
-                    col3,
-                    "total memory leaked",
-                    ByteSize::b(info.data.total.leaked),
-                );
-            });
+const TABLES: [TopTable; 4] = [
+    TopTable {
+        label: "Peak Contributions",
+        header: "Peak",
+        metric: |data| data.peak,
+        format: |v| ByteSize::b(v).to_string(),
+    },
+    TopTable {
+        label: "Largest Memory Leaks",
+        header: "Leaked",
+        metric: |data| data.leaked,
+        format: |v| ByteSize::b(v).to_string(),
+    },
+    TopTable {
+        label: "Most Memory Allocations",
+        header: "Allocations",
+        metric: |data| data.allocations,
+        format: |v| v.to_string(),
+    },
+    TopTable {
+        label: "Most Temporary Allocations",
+        header: "Temporary",
+        metric: |data| data.temporary,
+        format: |v| v.to_string(),
+    },
+];
+
+/// Summary of the trace and the allocation sites costing the most.
+pub struct OverviewPage {
+    grouping: Grouping,
+    top: usize,
+    show_all: bool,
+    /// Rows of each of `TABLES`, computed for the grouping in `computed`.
+    rows: Vec<Vec<Row>>,
+    computed: Option<Grouping>,
+    views: Vec<TableView>,
+    /// Growing buffers, found once asked for as they need the allocation events.
+    growth: Option<Result<Vec<GrowthSite>, String>>,
+}
+
+impl OverviewPage {
+    pub fn new() -> Self {
+        Self {
+            grouping: Grouping::Function,
+            top: 20,
+            show_all: false,
+            rows: Vec::new(),
+            computed: None,
+            views: TABLES.iter().map(TableView::new).collect(),
+            growth: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        let mut navigation = None;
+
+        ui.with_layout(Layout::default(), |ui| {
             ui.add_space(20.0);
-        });
-        ui.add_space(20.0);
-        ui.separator();
-        ui.add_space(20.0);
-        ui.horizontal(|ui| {
-            ui.add_space(10.0);
-            ui.columns(4, |columns| {
-                let [col1, col2, col3, col4] = columns.get_disjoint_mut([0, 1, 2, 3]).unwrap();
-
-                let peaks = make_top_peaks(&info.data);
-                col1.horizontal(|ui| {
-                    ui.add_space(10.0);
-                    add_table(ui, "Peak Contributions", ["Location", "Peak"], peaks);
-                    ui.add_space(10.0);
-                });
+            ui.horizontal(|ui| {
+                ui.add_space(20.0);
+                ui.columns(3, |columns| {
+                    let [col1, col2, col3] = columns.get_disjoint_mut([0, 1, 2]).unwrap();
 
-                let leaks = make_top_leaks(&info.data);
-                col2.horizontal(|ui| {
-                    ui.add_space(10.0);
-                    add_table(ui, "Largest Memory Leaks", ["Location", "Leaked"], leaks);
-                    ui.add_space(10.0);
-                });
+                    let total_ram = info.data.page_size * info.data.pages;
+
+                    add_key_value(col1, "application", &info.app_name);
+                    add_key_value(col1, "total runtime", format!("{:?}", info.data.duration));
+                    add_key_value(col1, "total system memory", ByteSize::b(total_ram));
 
-                let allocations = make_top_allocations(&info.data);
-                col3.horizontal(|ui| {
-                    ui.add_space(10.0);
-                    add_table(
-                        ui,
-                        "Most Memory Allocations",
-                        ["Location", "Allocations"],
-                        allocations,
+                    add_key_value(
+                        col2,
+                        "calls to allocation functions",
+                        info.data.total.allocations,
                     );
-                    ui.add_space(10.0);
-                });
+                    add_key_value(col2, "temporary allocations", info.data.total.temporary);
 
-                let tmp_allocations = make_top_tmp_allocations(&info.data);
-                col4.horizontal(|ui| {
-                    ui.add_space(10.0);
-                    add_table(
-                        ui,
-                        "Most Temporary Allocations",
-                        ["Location", "Temporary"],
-                        tmp_allocations,
+                    add_key_value(
+                        col3,
+                        "peak heap memory consumption",
+                        ByteSize::b(info.data.total.peak),
+                    );
+                    add_key_value(col3, "peak RSS", ByteSize::b(info.data.peak_rss));
+                    add_key_value(
+                        col3,
+                        "total memory leaked",
+                        ByteSize::b(info.data.total.leaked),
                     );
-                    ui.add_space(10.0);
                 });
+                ui.add_space(20.0);
+            });
+            ui.add_space(20.0);
+            ui.separator();
+            ui.add_space(20.0);
+            ui.horizontal(|ui| {
+                ui.add_space(20.0);
+                ComboBox::from_label("Group by")
+                    .selected_text(self.grouping.label())
+                    .show_ui(ui, |ui| {
+                        for grouping in [Grouping::Function, Grouping::Line, Grouping::Stack] {
+                            ui.selectable_value(&mut self.grouping, grouping, grouping.label());
+                        }
+                    });
+                ui.add_enabled(
+                    !self.show_all,
+                    DragValue::new(&mut self.top).range(1..=1000).prefix("top "),
+                );
+                ui.checkbox(&mut self.show_all, "Show all");
             });
             ui.add_space(10.0);
-        })
-    });
+
+            if self.computed != Some(self.grouping) {
+                self.rows = TABLES
+                    .iter()
+                    .map(|table| make_top(&info.data, self.grouping, table.metric))
+                    .collect();
+                self.computed = Some(self.grouping);
+                for view in &mut self.views {
+                    view.dirty = true;
+                }
+            }
+            let limit = if self.show_all { usize::MAX } else { self.top };
+
+            if info.has_trace() {
+                TopBottomPanel::bottom("growing_buffers")
+                    .resizable(true)
+                    .default_height(200.0)
+                    .show_inside(ui, |ui| {
+                        ui.add_space(5.0);
+                        ui.label("Growing Buffers");
+                        ui.label(
+                            RichText::new(
+                                "Buffers reallocated again and again to a larger size, such as a Vec filled by push.",
+                            )
+                            .weak(),
+                        );
+
+                        match &self.growth {
+                            Some(Ok(growth)) => {
+                                if let Some(nav) = show_growth(ui, info, symbol_options, growth) {
+                                    navigation = Some(nav);
+                                }
+                            }
+                            Some(Err(error)) => {
+                                ui.colored_label(ui.visuals().error_fg_color, error);
+                            }
+                            None => {
+                                if ui
+                                    .button("Find growing buffers")
+                                    .on_hover_text("Reads the allocation events of the trace")
+                                    .clicked()
+                                {
+                                    self.growth = Some(
+                                        info.events()
+                                            .map(|events| find_growing_buffers(&info.data, events))
+                                            .map_err(|err| {
+                                                format!("Failed to read allocation events: {err}")
+                                            }),
+                                    );
+                                }
+                            }
+                        }
+                    });
+            }
+
+            ui.horizontal(|ui| {
+                ui.add_space(10.0);
+                ui.columns(4, |columns| {
+                    let tables = columns.iter_mut().zip(&TABLES).zip(&self.rows);
+                    for (((col, table), rows), view) in tables.zip(&mut self.views) {
+                        col.horizontal(|ui| {
+                            ui.add_space(10.0);
+                            if let Some(nav) =
+                                view.show(ui, info, symbol_options, table, rows, limit)
+                            {
+                                navigation = Some(nav);
+                            }
+                            ui.add_space(10.0);
+                        });
+                    }
+                });
+                ui.add_space(10.0);
+            })
+        });
+
+        navigation
+    }
 }
 
-fn make_top_peaks(data: &AccumulatedData) -> Vec<(String, String)> {
-    let grouped = data
-        .allocations
-        .iter()
-        .map(|alloc| {
-            let trace = &data.traces[(alloc.trace_idx - 1) as usize];
-            let ip = &data.instruction_pointers[(trace.ip_idx - 1) as usize];
-            let fn_name = fn_name_from_frame(&data.strings, ip.frame.as_ref());
-            (fn_name, alloc.data.peak)
-        })
-        .into_grouping_map()
-        .sum();
-
-    let contributions = grouped
-        .iter()
-        .sorted_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(a.0)))
-        .map(|i| (i.0.to_string(), ByteSize::b(*i.1).to_string()))
-        .collect::<Vec<_>>();
-
-    contributions
-}
-
-fn make_top_leaks(data: &AccumulatedData) -> Vec<(String, String)> {
-    let grouped = data
-        .allocations
-        .iter()
-        .map(|alloc| {
-            let trace = &data.traces[(alloc.trace_idx - 1) as usize];
-            let ip = &data.instruction_pointers[(trace.ip_idx - 1) as usize];
-            let fn_name = fn_name_from_frame(&data.strings, ip.frame.as_ref());
-            (fn_name, alloc.data.leaked)
-        })
-        .into_grouping_map()
-        .sum();
-
-    let contributions = grouped
-        .iter()
-        .sorted_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(a.0)))
-        .map(|i| (i.0.to_string(), ByteSize::b(*i.1).to_string()))
-        .collect::<Vec<_>>();
-
-    contributions
-}
-
-fn make_top_allocations(data: &AccumulatedData) -> Vec<(String, String)> {
-    let grouped = data
-        .allocations
-        .iter()
-        .map(|alloc| {
-            let trace = &data.traces[(alloc.trace_idx - 1) as usize];
-            let ip = &data.instruction_pointers[(trace.ip_idx - 1) as usize];
-            let fn_name = fn_name_from_frame(&data.strings, ip.frame.as_ref());
-            (fn_name, alloc.data.allocations)
+struct Row {
+    location: String,
+    /// Shown on hover, the full call stack is shown when `None`.
+    details: Option<String>,
+    value: u64,
+    stack: StackRef,
+}
+
+/// Sums `metric` by allocation site, the heaviest allocation of each group represents it when
+/// navigating to the other pages.
+fn make_top(
+    data: &AccumulatedData,
+    grouping: Grouping,
+    metric: impl Fn(&AllocationData) -> u64,
+) -> Vec<Row> {
+    let mut grouped: HashMap<Site, (u64, u64, u64)> = HashMap::new();
+
+    for alloc in &data.allocations {
+        let Some(frame) = trace_frames(data, alloc.trace_idx).next() else {
+            continue;
+        };
+        let (fn_idx, file_idx, line_number) = frame_location(frame);
+        let site = match grouping {
+            Grouping::Function => Site::Function(fn_idx),
+            Grouping::Line if file_idx > 0 => Site::Line(file_idx, line_number),
+            Grouping::Line => Site::Function(fn_idx),
+            Grouping::Stack => Site::Stack(alloc.trace_idx),
+        };
+
+        let value = metric(&alloc.data);
+        let (total, heaviest, trace_idx) = grouped.entry(site).or_insert((0, 0, alloc.trace_idx));
+        *total += value;
+        if value > *heaviest {
+            *heaviest = value;
+            *trace_idx = alloc.trace_idx;
+        }
+    }
+
+    grouped
+        .into_values()
+        .filter(|(total, _, _)| *total > 0)
+        .map(|(total, _, trace_idx)| {
+            let (location, details) = describe_site(data, grouping, trace_idx);
+            (total, location, details, trace_idx)
         })
-        .into_grouping_map()
-        .sum();
-
-    let contributions = grouped
-        .iter()
-        .sorted_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(a.0)))
-        .map(|i| (i.0.to_string(), i.1.to_string()))
-        .collect::<Vec<_>>();
-
-    contributions
-}
-
-fn make_top_tmp_allocations(data: &AccumulatedData) -> Vec<(String, String)> {
-    let grouped = data
-        .allocations
-        .iter()
-        .map(|alloc| {
-            let trace = &data.traces[(alloc.trace_idx - 1) as usize];
-            let ip = &data.instruction_pointers[(trace.ip_idx - 1) as usize];
-            let fn_name = fn_name_from_frame(&data.strings, ip.frame.as_ref());
-            (fn_name, alloc.data.temporary)
+        .sorted_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)))
+        .map(|(total, location, details, trace_idx)| Row {
+            location,
+            details,
+            value: total,
+            stack: StackRef::full(data, trace_idx),
         })
-        .into_grouping_map()
-        .sum();
+        .collect()
+}
 
-    let contributions = grouped
-        .iter()
-        .sorted_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(a.0)))
-        .map(|i| (i.0.to_string(), i.1.to_string()))
-        .collect::<Vec<_>>();
+/// Label of the allocation site of a trace and the text shown when hovering it.
+fn describe_site(
+    data: &AccumulatedData,
+    grouping: Grouping,
+    trace_idx: u64,
+) -> (String, Option<String>) {
+    let mut frames = trace_frames(data, trace_idx);
+    let frame = frames.next();
+    let fn_name = fn_name_from_frame(&data.strings, frame).to_string();
 
-    contributions
+    match grouping {
+        Grouping::Function => (fn_name.clone(), Some(fn_name)),
+        Grouping::Line => match frame.and_then(|frame| frame_file(data, frame)) {
+            Some((file_name, line_number)) => (format!("{file_name}:{line_number}"), Some(fn_name)),
+            None => (fn_name.clone(), Some(fn_name)),
+        },
+        Grouping::Stack => match frames.next() {
+            Some(caller) => {
+                let caller = fn_name_from_frame(&data.strings, Some(caller));
+                (format!("{fn_name} ← {caller}"), None)
+            }
+            None => (fn_name, None),
+        },
+    }
 }
 
-fn add_table(
+/// Call sites growing buffers one reallocation at a time.
+fn show_growth(
     ui: &mut Ui,
-    label: &str,
-    headers: [&str; 2],
-    data: impl IntoIterator<Item = (String, String)>,
-) {
+    info: &MemInfo,
+    symbol_options: SymbolOptions,
+    sites: &[GrowthSite],
+) -> Option<Navigation> {
     const HEIGHT: f32 = 20.0;
 
-    ui.push_id(Instant::now(), |ui| {
-        ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
-            ui.label(label);
-            TableBuilder::new(ui)
-                .striped(true)
-                .resizable(true)
-                .cell_layout(Layout::left_to_right(Align::Center))
-                .column(Column::remainder().clip(true))
-                .column(Column::remainder())
-                .header(HEIGHT, |mut header| {
-                    for header_label in headers {
-                        header.col(|ui| {
-                            ui.label(header_label);
+    let mut navigation = None;
+    TableBuilder::new(ui)
+        .id_salt("growing_buffers")
+        .striped(true)
+        .resizable(true)
+        .auto_shrink(false)
+        .sense(Sense::click())
+        .cell_layout(Layout::left_to_right(Align::Center))
+        .column(Column::remainder().at_least(200.0).clip(true))
+        .columns(Column::auto().at_least(80.0), 4)
+        .column(Column::remainder().clip(true))
+        .header(HEIGHT, |mut header| {
+            for label in [
+                "Location",
+                "Buffers",
+                "Reallocations",
+                "Copied",
+                "Final size",
+                "Hint",
+            ] {
+                header.col(|ui| {
+                    ui.label(label);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(HEIGHT, sites.len(), |mut row| {
+                let site = &sites[row.index()];
+                let stack = StackRef::full(&info.data, site.trace_idx);
+
+                row.col(|ui| {
+                    let frame = owner_frame(&info.data, site.trace_idx);
+                    let name = fn_name_from_frame(&info.data.strings, frame);
+                    ui.label(symbols::prettify(name, symbol_options).as_ref())
+                        .on_hover_ui(|ui| {
+                            ui.label(stack.to_text(&info.data));
                         });
+                });
+                row.col(|ui| {
+                    ui.label(site.buffers.to_string());
+                });
+                row.col(|ui| {
+                    ui.label(site.reallocations.to_string());
+                });
+                row.col(|ui| {
+                    ui.label(ByteSize::b(site.copied).to_string());
+                });
+                row.col(|ui| {
+                    ui.label(ByteSize::b(site.max_final_size).to_string())
+                        .on_hover_text(format!(
+                            "average: {}",
+                            ByteSize::b(site.average_final_size())
+                        ));
+                });
+                row.col(|ui| {
+                    ui.label(
+                        RichText::new(format!(
+                            "Reserve up to {} with with_capacity",
+                            ByteSize::b(site.max_final_size)
+                        ))
+                        .weak(),
+                    );
+                });
+
+                let response = row.response();
+                if response.double_clicked() {
+                    navigation = Some(Navigation::Details(stack));
+                }
+                response.context_menu(|ui| {
+                    if let Some(nav) = stack_menu(ui, &info.data, stack) {
+                        navigation = Some(nav);
                     }
-                })
-                .body(|mut body| {
-                    for a in data {
-                        body.row(HEIGHT, |mut row| {
+                });
+            });
+        });
+
+    navigation
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum SortColumn {
+    Location,
+    Value,
+}
+
+/// Sorting and filtering of one of the top tables.
+struct TableView {
+    sort: SortColumn,
+    descending: bool,
+    filter: String,
+    /// Indices of the rows matching the filter, in display order.
+    order: Vec<usize>,
+    dirty: bool,
+    export_path: String,
+    export_status: Option<String>,
+}
+
+impl TableView {
+    fn new(table: &TopTable) -> Self {
+        Self {
+            sort: SortColumn::Value,
+            descending: true,
+            filter: String::new(),
+            order: Vec::new(),
+            dirty: true,
+            export_path: format!("{}.csv", table.header.to_lowercase()),
+            export_status: None,
+        }
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+        table: &TopTable,
+        rows: &[Row],
+        limit: usize,
+    ) -> Option<Navigation> {
+        const HEIGHT: f32 = 20.0;
+
+        let mut navigation = None;
+        let total = (table.metric)(&info.data.total);
+
+        ui.push_id(table.label, |ui| {
+            ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(table.label);
+                    ui.menu_button("Export", |ui| {
+                        self.show_export_menu(ui, info, table, rows, total);
+                    });
+                });
+                if ui
+                    .add(TextEdit::singleline(&mut self.filter).hint_text("Filter"))
+                    .changed()
+                {
+                    self.dirty = true;
+                }
+
+                if self.dirty {
+                    self.update_order(rows);
+                }
+                let order = &self.order[..self.order.len().min(limit)];
+
+                let mut clicked = None;
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .resizable(true)
+                    .sense(Sense::click())
+                    .cell_layout(Layout::left_to_right(Align::Center))
+                    .column(Column::remainder().clip(true))
+                    .column(Column::auto().at_least(60.0))
+                    .column(Column::auto().at_least(50.0))
+                    .header(HEIGHT, |mut header| {
+                        for (column, label) in [
+                            (SortColumn::Location, "Location"),
+                            (SortColumn::Value, table.header),
+                        ] {
+                            header.col(|ui| {
+                                let arrow = match (self.sort == column, self.descending) {
+                                    (true, true) => " ⏷",
+                                    (true, false) => " ⏶",
+                                    (false, _) => "",
+                                };
+                                if ui.button(format!("{label}{arrow}")).clicked() {
+                                    clicked = Some(column);
+                                }
+                            });
+                        }
+                        header.col(|ui| {
+                            ui.label("%");
+                        });
+                    })
+                    .body(|body| {
+                        body.rows(HEIGHT, order.len(), |mut row| {
+                            let a = &rows[order[row.index()]];
+                            row.col(|ui| {
+                                let name = symbols::prettify(&a.location, symbol_options);
+                                let response = ui.label(name.as_ref());
+                                match &a.details {
+                                    Some(details) if name != details.as_str() => {
+                                        response.on_hover_text(details);
+                                    }
+                                    Some(_) => {}
+                                    None => {
+                                        response.on_hover_ui(|ui| {
+                                            ui.label(a.stack.to_text(&info.data));
+                                        });
+                                    }
+                                }
+                            });
                             row.col(|ui| {
-                                ui.label(a.0);
+                                ui.label((table.format)(a.value));
                             });
                             row.col(|ui| {
-                                ui.label(a.1);
+                                ui.label(format!("{:.2}", percentage(a.value, total)));
+                            });
+
+                            let response = row.response();
+                            if response.double_clicked() {
+                                navigation = Some(Navigation::Details(a.stack));
+                            }
+                            response.context_menu(|ui| {
+                                if let Some(nav) = stack_menu(ui, &info.data, a.stack) {
+                                    navigation = Some(nav);
+                                }
                             });
-                        })
+                        });
+                    });
+
+                if let Some(column) = clicked {
+                    if self.sort == column {
+                        self.descending = !self.descending;
+                    } else {
+                        self.sort = column;
+                        self.descending = column == SortColumn::Value;
                     }
+                    self.dirty = true;
+                }
+            });
+        });
+
+        navigation
+    }
+
+    /// Exports the rows matching the filter, in display order and ignoring the top N limit.
+    fn show_export_menu(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        table: &TopTable,
+        rows: &[Row],
+        total: u64,
+    ) {
+        if ui.button("Copy as CSV").clicked() {
+            ui.ctx().copy_text(self.to_csv(info, table, rows, total));
+            ui.close_menu();
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Save as CSV").clicked() {
+                let csv = self.to_csv(info, table, rows, total);
+                self.export_status = Some(match fs::write(&self.export_path, csv) {
+                    Ok(()) => format!("Saved {}", self.export_path),
+                    Err(err) => format!("Failed to save {}: {err}", self.export_path),
                 });
+            }
         });
-    });
+        if let Some(status) = &self.export_status {
+            ui.label(status);
+        }
+    }
+
+    fn update_order(&mut self, rows: &[Row]) {
+        let filter = self.filter.to_lowercase();
+        let matches = |row: &Row| {
+            row.location.to_lowercase().contains(&filter)
+                || row
+                    .details
+                    .as_ref()
+                    .is_some_and(|details| details.to_lowercase().contains(&filter))
+        };
+
+        self.order = (0..rows.len())
+            .filter(|idx| filter.is_empty() || matches(&rows[*idx]))
+            .collect();
+
+        // rows are already sorted by decreasing value
+        match self.sort {
+            SortColumn::Location => self
+                .order
+                .sort_by(|a, b| rows[*a].location.cmp(&rows[*b].location)),
+            SortColumn::Value => {}
+        }
+        if self.sort == SortColumn::Location && self.descending
+            || self.sort == SortColumn::Value && !self.descending
+        {
+            self.order.reverse();
+        }
+        self.dirty = false;
+    }
+
+    fn to_csv(&self, info: &MemInfo, table: &TopTable, rows: &[Row], total: u64) -> String {
+        let mut csv = format!(
+            "location,function,{},percentage\n",
+            table.header.to_lowercase()
+        );
+        for idx in &self.order {
+            let row = &rows[*idx];
+            let function = row.details.as_deref().unwrap_or_else(|| {
+                trace_frames(&info.data, row.stack.trace_idx)
+                    .next()
+                    .map_or("", |frame| {
+                        fn_name_from_frame(&info.data.strings, Some(frame))
+                    })
+            });
+            csv.push_str(&format!(
+                "{},{},{},{:.4}\n",
+                csv_field(&row.location),
+                csv_field(function),
+                row.value,
+                percentage(row.value, total)
+            ));
+        }
+        csv
+    }
+}
+
+fn percentage(value: u64, total: u64) -> f64 {
+    if total > 0 {
+        value as f64 / total as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
 }
 
 pub fn fn_name_from_frame<'a>(dict: &'a [String], frame: Option<&Frame>) -> &'a str {
@@ -0,0 +1,273 @@
+use crate::ui::MemInfo;
+use crate::ui::helpers::show_events;
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::stack::{Navigation, StackRef, frame_file, stack_menu, trace_frames};
+use crate::ui::symbols::{self, SymbolOptions};
+use crate::ui::widgets::histogram::{Bar, Histogram};
+use bytesize::ByteSize;
+use egui::{Align, ComboBox, Layout, RichText, Sense, Ui};
+use egui_extras::{Column, TableBuilder};
+use memtrace_utils::parser::AccumulatedData;
+use std::collections::HashMap;
+
+const HISTOGRAM_HEIGHT: f32 = 220.0;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum BarHeight {
+    Count,
+    Bytes,
+}
+
+/// Allocations whose size is in `[2^(n-1), 2^n)` for bucket `n`, bucket 0 holding the empty ones.
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    count: u64,
+    bytes: u64,
+}
+
+struct SiteRow {
+    function: String,
+    location: String,
+    count: u64,
+    bytes: u64,
+    stack: StackRef,
+}
+
+/// Distribution of allocation sizes in log2 buckets, and the call sites of a bucket.
+pub struct SizesPage {
+    bar_height: BarHeight,
+    /// Allocations made with each of `AccumulatedData::allocation_infos`.
+    counts: Vec<u64>,
+    buckets: Vec<Bucket>,
+    computed: bool,
+    selected: Option<usize>,
+    sites: Vec<SiteRow>,
+}
+
+impl SizesPage {
+    pub fn new() -> Self {
+        Self {
+            bar_height: BarHeight::Count,
+            counts: Vec::new(),
+            buckets: Vec::new(),
+            computed: false,
+            selected: None,
+            sites: Vec::new(),
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        let events = show_events(ui, info)?;
+
+        if !self.computed {
+            self.counts = events.alloc_counts(info.data.allocation_infos.len());
+            self.buckets = make_buckets(&info.data, &self.counts);
+            self.computed = true;
+        }
+
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Bar height")
+                .selected_text(format!("{:?}", self.bar_height))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.bar_height, BarHeight::Count, "Count");
+                    ui.selectable_value(&mut self.bar_height, BarHeight::Bytes, "Bytes");
+                });
+            ui.label(RichText::new("Click a bucket to list its call sites.").weak());
+        });
+        ui.separator();
+
+        let bars = self
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(idx, bucket)| Bar {
+                label: short_size(bucket_start(idx)),
+                value: match self.bar_height {
+                    BarHeight::Count => bucket.count as f64,
+                    BarHeight::Bytes => bucket.bytes as f64,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let response = Histogram::new(&bars, self.selected, HISTOGRAM_HEIGHT).show(ui);
+        if let Some(idx) = response.hovered {
+            let bucket = self.buckets[idx];
+            response.response.on_hover_ui_at_pointer(|ui| {
+                ui.label(RichText::new(bucket_range(idx)).strong());
+                ui.label(format!("allocations: {}", bucket.count));
+                ui.label(format!("total: {}", ByteSize::b(bucket.bytes)));
+            });
+        }
+        if let Some(idx) = response.clicked {
+            self.selected = Some(idx);
+            self.sites = make_sites(&info.data, &self.counts, idx);
+        }
+
+        ui.separator();
+        match self.selected {
+            Some(idx) => {
+                ui.label(
+                    RichText::new(format!("Call sites allocating {}", bucket_range(idx))).strong(),
+                );
+                self.show_sites(ui, info, symbol_options)
+            }
+            None => None,
+        }
+    }
+
+    fn show_sites(
+        &self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        symbol_options: SymbolOptions,
+    ) -> Option<Navigation> {
+        const HEIGHT: f32 = 20.0;
+
+        let mut navigation = None;
+        TableBuilder::new(ui)
+            .id_salt("size_sites")
+            .striped(true)
+            .resizable(true)
+            .auto_shrink(false)
+            .sense(Sense::click())
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .column(Column::remainder().at_least(200.0).clip(true))
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .columns(Column::auto().at_least(80.0), 2)
+            .header(HEIGHT, |mut header| {
+                for label in ["Function", "Location", "Allocations", "Bytes"] {
+                    header.col(|ui| {
+                        ui.strong(label);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(HEIGHT, self.sites.len(), |mut row| {
+                    let site = &self.sites[row.index()];
+                    row.col(|ui| {
+                        let name = symbols::prettify(&site.function, symbol_options);
+                        let response = ui.label(name.as_ref());
+                        response.on_hover_ui(|ui| {
+                            ui.label(site.stack.to_text(&info.data));
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.label(&site.location);
+                    });
+                    row.col(|ui| {
+                        ui.label(site.count.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(ByteSize::b(site.bytes).to_string());
+                    });
+
+                    let response = row.response();
+                    if response.double_clicked() {
+                        navigation = Some(Navigation::Details(site.stack));
+                    }
+                    response.context_menu(|ui| {
+                        if let Some(nav) = stack_menu(ui, &info.data, site.stack) {
+                            navigation = Some(nav);
+                        }
+                    });
+                });
+            });
+
+        navigation
+    }
+}
+
+fn bucket_of(size: u64) -> usize {
+    match size {
+        0 => 0,
+        _ => size.ilog2() as usize + 1,
+    }
+}
+
+fn bucket_start(idx: usize) -> u64 {
+    match idx {
+        0 => 0,
+        _ => 1 << (idx - 1),
+    }
+}
+
+fn bucket_range(idx: usize) -> String {
+    match idx {
+        0 => "0 B".to_string(),
+        1 => "1 B".to_string(),
+        _ => format!(
+            "{} to {}",
+            ByteSize::b(bucket_start(idx)),
+            ByteSize::b((1 << idx) - 1)
+        ),
+    }
+}
+
+/// Compact power-of-two size for the axis labels, such as `512` or `4K`.
+fn short_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["", "K", "M", "G", "T"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024 && unit + 1 < UNITS.len() {
+        value /= 1024;
+        unit += 1;
+    }
+    format!("{}{}", value, UNITS[unit])
+}
+
+fn make_buckets(data: &AccumulatedData, counts: &[u64]) -> Vec<Bucket> {
+    let mut buckets = Vec::new();
+
+    for (info, count) in data.allocation_infos.iter().zip(counts) {
+        let idx = bucket_of(info.size);
+        if buckets.len() <= idx {
+            buckets.resize(idx + 1, Bucket::default());
+        }
+        buckets[idx].count += count;
+        buckets[idx].bytes += count * info.size;
+    }
+
+    buckets
+}
+
+/// Call sites of the allocations of a bucket, the ones allocating the most bytes first.
+fn make_sites(data: &AccumulatedData, counts: &[u64], bucket: usize) -> Vec<SiteRow> {
+    let mut grouped: HashMap<u64, (u64, u64)> = HashMap::new();
+
+    for (info, count) in data.allocation_infos.iter().zip(counts) {
+        if *count > 0 && bucket_of(info.size) == bucket {
+            let (total_count, bytes) = grouped.entry(info.allocation_idx).or_default();
+            *total_count += count;
+            *bytes += count * info.size;
+        }
+    }
+
+    let mut sites = grouped
+        .into_iter()
+        .map(|(allocation_idx, (count, bytes))| {
+            let trace_idx = data.allocations[allocation_idx as usize].trace_idx;
+            let frame = trace_frames(data, trace_idx).next();
+            let location = frame
+                .and_then(|frame| frame_file(data, frame))
+                .map(|(file_name, line_number)| format!("{file_name}:{line_number}"))
+                .unwrap_or_default();
+
+            SiteRow {
+                function: fn_name_from_frame(&data.strings, frame).to_string(),
+                location,
+                count,
+                bytes,
+                stack: StackRef::full(data, trace_idx),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    sites.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.count.cmp(&a.count)));
+    sites
+}
@@ -0,0 +1,128 @@
+use crate::ui::sources::SourceResolver;
+use egui::Context;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+
+/// Bytes of source code kept in memory before the least recently used files are dropped.
+const MAX_CACHED_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    Loading,
+    Loaded(Arc<str>),
+    NotFound,
+}
+
+struct Entry {
+    source: Source,
+    last_used: u64,
+}
+
+type Loaded = (String, Option<String>);
+
+/// Source files resolved and read on background threads, so that slow file systems do not
+/// block the UI. Files that were not found are remembered too.
+pub struct SourceCache {
+    entries: HashMap<String, Entry>,
+    clock: u64,
+    size: usize,
+    sender: Sender<Loaded>,
+    receiver: Receiver<Loaded>,
+}
+
+impl SourceCache {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            entries: HashMap::new(),
+            clock: 0,
+            size: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Returns the file if it is cached, starting to load it otherwise.
+    pub fn get(&mut self, ctx: &Context, resolver: &SourceResolver, file_name: &str) -> Source {
+        self.receive();
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(file_name) {
+            entry.last_used = self.clock;
+            return entry.source.clone();
+        }
+
+        let sender = self.sender.clone();
+        let ctx = ctx.clone();
+        let resolver = resolver.clone();
+        let name = file_name.to_string();
+        thread::spawn(move || {
+            let code = resolver
+                .resolve(&name)
+                .and_then(|path| fs::read_to_string(path).ok());
+            if sender.send((name, code)).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+
+        self.entries.insert(
+            file_name.to_string(),
+            Entry {
+                source: Source::Loading,
+                last_used: self.clock,
+            },
+        );
+
+        Source::Loading
+    }
+
+    /// Looks up the files that were not found again, e.g. after the resolver changed.
+    pub fn forget_missing(&mut self) {
+        self.entries
+            .retain(|_, entry| !matches!(entry.source, Source::NotFound));
+    }
+
+    fn receive(&mut self) {
+        while let Ok((file_name, code)) = self.receiver.try_recv() {
+            let Some(entry) = self.entries.get_mut(&file_name) else {
+                continue;
+            };
+
+            entry.source = match code {
+                Some(code) => {
+                    self.size += code.len();
+                    Source::Loaded(code.into())
+                }
+                None => Source::NotFound,
+            };
+        }
+
+        self.evict();
+    }
+
+    /// Drops the least recently used files above the size limit, keeping the file in use.
+    fn evict(&mut self) {
+        while self.size > MAX_CACHED_BYTES {
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.last_used < self.clock)
+                .filter_map(|(name, entry)| match &entry.source {
+                    Source::Loaded(code) => Some((name, entry.last_used, code.len())),
+                    _ => None,
+                })
+                .min_by_key(|(_, last_used, _)| *last_used)
+                .map(|(name, _, size)| (name.clone(), size));
+
+            let Some((name, size)) = oldest else {
+                break;
+            };
+            self.entries.remove(&name);
+            self.size -= size;
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Trailing components of a recorded path that must match below a search directory.
+const MIN_SUFFIX_COMPONENTS: usize = 2;
+
+/// Finds the local copy of a source file recorded in a trace, which is often made on another
+/// machine, in a container or on CI.
+#[derive(Debug, Clone, Default)]
+pub struct SourceResolver {
+    /// Prefix replacements tried in order, e.g. `/build/ci/workspace` → `~/src/project`.
+    pub remaps: Vec<(String, String)>,
+    /// Directories searched for the trailing components of the recorded path.
+    pub search_dirs: Vec<PathBuf>,
+}
+
+impl SourceResolver {
+    /// Parses a `FROM=TO` remap as accepted on the command line.
+    pub fn parse_remap(remap: &str) -> Option<(String, String)> {
+        let (from, to) = remap.split_once('=')?;
+        if from.is_empty() {
+            return None;
+        }
+        Some((from.to_string(), to.to_string()))
+    }
+
+    pub fn resolve(&self, file_name: &str) -> Option<PathBuf> {
+        if file_name.is_empty() {
+            return None;
+        }
+
+        let original = Path::new(file_name);
+        if original.is_file() {
+            return Some(original.to_path_buf());
+        }
+
+        for (from, to) in &self.remaps {
+            // whole components only, so that `/build/a` does not remap `/build/ab/lib.rs`
+            let Ok(rest) = original.strip_prefix(from) else {
+                continue;
+            };
+            let path = expand_home(to).join(rest);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+
+        if let Some(path) = resolve_std(file_name).or_else(|| resolve_cargo_home(file_name)) {
+            return Some(path);
+        }
+
+        let components = original
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect::<Vec<_>>();
+        if components.is_empty() {
+            return None;
+        }
+
+        // a bare file name such as `lib.rs` or `mod.rs` would match an unrelated file
+        let max_skip = components.len().saturating_sub(MIN_SUFFIX_COMPONENTS);
+        for dir in &self.search_dirs {
+            let dir = expand_home(&dir.to_string_lossy());
+            for skip in 0..=max_skip {
+                let path = components[skip..]
+                    .iter()
+                    .fold(dir.clone(), |path, c| path.join(c));
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Maps `/rustc/<commit>/library/...` to the rust-src component of a local toolchain,
+/// preferring the toolchain built from the same commit.
+fn resolve_std(file_name: &str) -> Option<PathBuf> {
+    let rest = file_name.strip_prefix("/rustc/")?;
+    let (commit, rest) = rest.split_once('/')?;
+
+    let mut roots = rust_src_roots().iter().collect::<Vec<_>>();
+    roots.sort_by_key(|root| {
+        !root
+            .commit
+            .as_ref()
+            .is_some_and(|short| commit.starts_with(short.as_str()))
+    });
+
+    roots
+        .into_iter()
+        .map(|root| root.path.join(rest))
+        .find(|path| path.is_file())
+}
+
+/// Maps sources of dependencies unpacked by cargo on another machine, e.g.
+/// `/home/ci/.cargo/registry/src/<index>/serde-1.0.0/src/de.rs`, to the local cargo home.
+/// The index directory differs between cargo versions, so every local index is tried.
+fn resolve_cargo_home(file_name: &str) -> Option<PathBuf> {
+    let cargo_home = cargo_home()?;
+
+    if let Some((_, rest)) = file_name.split_once("/registry/src/") {
+        let (_, crate_path) = rest.split_once('/')?;
+        let indexes = fs::read_dir(cargo_home.join("registry/src")).ok()?;
+
+        return indexes
+            .flatten()
+            .map(|index| index.path().join(crate_path))
+            .find(|path| path.is_file());
+    }
+
+    if let Some((_, rest)) = file_name.split_once("/git/checkouts/") {
+        let path = cargo_home.join("git/checkouts").join(rest);
+        return path.is_file().then_some(path);
+    }
+
+    None
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    match env::var_os("CARGO_HOME") {
+        Some(home) => Some(PathBuf::from(home)),
+        None => Some(PathBuf::from(env::var_os("HOME")?).join(".cargo")),
+    }
+}
+
+/// Root of the standard library sources of a toolchain.
+struct RustSrc {
+    /// Short commit hash of the toolchain's rustc when known.
+    commit: Option<String>,
+    path: PathBuf,
+}
+
+/// Sources of the default toolchain first, then of every other rustup toolchain.
+fn rust_src_roots() -> &'static [RustSrc] {
+    static ROOTS: OnceLock<Vec<RustSrc>> = OnceLock::new();
+
+    ROOTS.get_or_init(|| {
+        let mut sysroots = Vec::new();
+
+        if let Ok(output) = Command::new("rustc").args(["--print", "sysroot"]).output()
+            && output.status.success()
+        {
+            let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            sysroots.push(PathBuf::from(sysroot));
+        }
+
+        let rustup_home = env::var_os("RUSTUP_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".rustup")));
+        if let Some(toolchains) =
+            rustup_home.and_then(|home| fs::read_dir(home.join("toolchains")).ok())
+        {
+            for toolchain in toolchains.flatten() {
+                let path = toolchain.path();
+                if !sysroots.contains(&path) {
+                    sysroots.push(path);
+                }
+            }
+        }
+
+        sysroots
+            .into_iter()
+            .map(|sysroot| RustSrc {
+                commit: toolchain_commit(&sysroot),
+                path: sysroot.join("lib/rustlib/src/rust"),
+            })
+            .filter(|root| root.path.is_dir())
+            .collect()
+    })
+}
+
+/// Reads the short commit hash from the rustup manifest, e.g. `version = "1.85.0 (4d91de4e4 2025-02-17)"`.
+fn toolchain_commit(sysroot: &Path) -> Option<String> {
+    let manifest =
+        fs::read_to_string(sysroot.join("lib/rustlib/multirust-channel-manifest.toml")).ok()?;
+    let (_, pkg) = manifest.split_once("[pkg.rustc]")?;
+    let version = pkg
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("version = "))?;
+    let (_, commit) = version.split_once('(')?;
+    let commit = commit.split_whitespace().next()?;
+
+    Some(commit.to_string())
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
+}
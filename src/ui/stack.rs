@@ -0,0 +1,196 @@
+use crate::ui::overview::fn_name_from_frame;
+use crate::ui::symbols::{self, Origin};
+use egui::Ui;
+use memtrace_utils::parser::{AccumulatedData, Frame, InstructionPointer};
+use std::ptr;
+
+/// The outermost `depth` frames of the call stack of a trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackRef {
+    pub trace_idx: u64,
+    pub depth: usize,
+}
+
+impl StackRef {
+    /// The whole call stack of a trace, down to the allocation site.
+    pub fn full(data: &AccumulatedData, trace_idx: u64) -> Self {
+        Self {
+            trace_idx,
+            depth: trace_frames(data, trace_idx).count(),
+        }
+    }
+
+    /// Frames of the stack from the outermost caller down to the referenced frame.
+    pub fn frames(self, data: &AccumulatedData) -> Vec<&Frame> {
+        self.inline_frames(data)
+            .into_iter()
+            .map(|(frame, _)| frame)
+            .collect()
+    }
+
+    /// Frames of the stack in the order of `frames`, with whether each one is inlined into its
+    /// caller.
+    pub fn inline_frames(self, data: &AccumulatedData) -> Vec<(&Frame, bool)> {
+        let mut frames = trace_inline_frames(data, self.trace_idx).collect::<Vec<_>>();
+        frames.reverse();
+        frames.truncate(self.depth);
+        frames
+    }
+
+    /// Source location of the referenced frame.
+    pub fn location(self, data: &AccumulatedData) -> Option<(String, u32)> {
+        let frames = self.frames(data);
+        let (file_name, line_number) = frame_file(data, frames.last()?)?;
+        Some((file_name.to_string(), line_number))
+    }
+
+    /// Backtrace-like text, innermost frame first, with source locations when known.
+    pub fn to_text(self, data: &AccumulatedData) -> String {
+        self.inline_frames(data)
+            .iter()
+            .rev()
+            .map(|(frame, inlined)| {
+                let name = fn_name_from_frame(&data.strings, Some(frame));
+                let name = if *inlined {
+                    format!("{name} [inlined]")
+                } else {
+                    name.to_string()
+                };
+                match frame_file(data, frame) {
+                    Some((file_name, line_number)) => {
+                        format!("{name}\n    at {file_name}:{line_number}")
+                    }
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A request to show a call stack in another page.
+#[derive(Debug, Clone)]
+pub enum Navigation {
+    TopDown(StackRef),
+    Flamegraph(StackRef),
+    Details(StackRef),
+    OpenInEditor { file_name: String, line_number: u32 },
+}
+
+/// Context menu entries of a call stack.
+pub fn stack_menu(ui: &mut Ui, data: &AccumulatedData, stack: StackRef) -> Option<Navigation> {
+    let mut navigation = None;
+    if ui.button("Show details").clicked() {
+        navigation = Some(Navigation::Details(stack));
+    }
+    if ui.button("Show in TopDown").clicked() {
+        navigation = Some(Navigation::TopDown(stack));
+    }
+    if ui.button("Show in Flamegraph").clicked() {
+        navigation = Some(Navigation::Flamegraph(stack));
+    }
+    if let Some((file_name, line_number)) = stack.location(data)
+        && ui.button("Open in editor").clicked()
+    {
+        navigation = Some(Navigation::OpenInEditor {
+            file_name,
+            line_number,
+        });
+    }
+    if ui.button("Copy stack").clicked() {
+        ui.ctx().copy_text(stack.to_text(data));
+        ui.close_menu();
+    }
+
+    if navigation.is_some() {
+        ui.close_menu();
+    }
+    navigation
+}
+
+/// Instruction pointers of a trace from the allocation site up to the outermost caller.
+pub fn trace_ips(
+    data: &AccumulatedData,
+    mut trace_idx: u64,
+) -> impl Iterator<Item = &InstructionPointer> {
+    std::iter::from_fn(move || {
+        if trace_idx == 0 {
+            return None;
+        }
+
+        let trace = &data.traces[trace_idx as usize - 1];
+        trace_idx = trace.parent_idx;
+
+        Some(&data.instruction_pointers[trace.ip_idx as usize - 1])
+    })
+}
+
+/// Frames of a trace from the allocation site up to the outermost caller.
+pub fn trace_frames(data: &AccumulatedData, trace_idx: u64) -> impl Iterator<Item = &Frame> {
+    trace_ips(data, trace_idx).flat_map(ip_frames)
+}
+
+/// Frames of a trace in the order of `trace_frames`, with whether each one is inlined into its
+/// caller.
+pub fn trace_inline_frames(
+    data: &AccumulatedData,
+    trace_idx: u64,
+) -> impl Iterator<Item = (&Frame, bool)> {
+    trace_ips(data, trace_idx).flat_map(ip_inline_frames)
+}
+
+/// Innermost frame of a trace outside the standard library, or the allocation site when the
+/// whole stack is std.
+pub fn owner_frame(data: &AccumulatedData, trace_idx: u64) -> Option<&Frame> {
+    let mut owner = None;
+    for frame in trace_frames(data, trace_idx) {
+        let name = fn_name_from_frame(&data.strings, Some(frame));
+        let file_name = frame_file(data, frame).map(|(file_name, _)| file_name);
+        if owner.is_none() {
+            owner = Some(frame);
+        }
+        if !name.is_empty() && symbols::origin(name, file_name) != Origin::Std {
+            return Some(frame);
+        }
+    }
+    owner
+}
+
+/// Frames of an instruction pointer from the innermost inlined function out to the function
+/// the instruction belongs to. The resolver records them in this order, the first one as
+/// `frame` and the callers it was inlined into as `inlined`, so every frame but the last is
+/// inlined into the one after it. Reverse it to walk from the callers down.
+pub fn ip_frames(ip_info: &InstructionPointer) -> impl DoubleEndedIterator<Item = &Frame> {
+    ip_info.frame.as_ref().into_iter().chain(&ip_info.inlined)
+}
+
+/// Frames of an instruction pointer in the order of `ip_frames`, with whether each one is
+/// inlined into its caller.
+pub fn ip_inline_frames(
+    ip_info: &InstructionPointer,
+) -> impl DoubleEndedIterator<Item = (&Frame, bool)> {
+    let outer = ip_info.inlined.last().or(ip_info.frame.as_ref());
+    ip_frames(ip_info).map(move |frame| (frame, !outer.is_some_and(|outer| ptr::eq(outer, frame))))
+}
+
+/// Function, file and line indices of a frame, zero when unknown.
+pub fn frame_location(frame: &Frame) -> (usize, usize, u32) {
+    match frame {
+        Frame::Single { function_idx } => (*function_idx, 0, 0),
+        Frame::Multiple {
+            function_idx,
+            file_idx,
+            line_number,
+        } => (*function_idx, *file_idx, *line_number),
+    }
+}
+
+pub fn frame_file<'a>(data: &'a AccumulatedData, frame: &Frame) -> Option<(&'a str, u32)> {
+    let (_, file_idx, line_number) = frame_location(frame);
+
+    if file_idx > 0 && file_idx <= data.strings.len() {
+        Some((&data.strings[file_idx - 1], line_number))
+    } else {
+        None
+    }
+}
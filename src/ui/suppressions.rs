@@ -0,0 +1,283 @@
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Leaks of one-time globals. Thread locals are only matched by their lazy initialisation, leaks
+/// of the closures run by `LocalKey::with` stay visible.
+const DEFAULT_PATTERNS: &str = r"
+fn:lazy_static
+fn:::OnceLock<.*>::initialize
+fn:::OnceCell<.*>::get_or_init
+fn:::LazyLock<.*>::force
+fn:::LazyCell<.*>::force
+fn:^std::(thread::local|sys::.*thread_local)::.*::(try_)?initialize$
+";
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches stacks with a function matching the regex.
+    Function(Regex),
+    /// Matches stacks whose functions, outermost first and joined by `;`, match the regex.
+    Stack(Regex),
+    /// Matches stacks with a function matching the glob.
+    FunctionGlob(String),
+    /// Matches stacks whose functions, outermost first and joined by `;`, match the glob.
+    StackGlob(String),
+}
+
+impl Pattern {
+    pub fn matches(&self, functions: &[&str]) -> bool {
+        match self {
+            Pattern::Function(regex) => functions.iter().any(|name| regex.is_match(name)),
+            Pattern::Stack(regex) => regex.is_match(&functions.join(";")),
+            Pattern::FunctionGlob(glob) => functions.iter().any(|name| glob_match(glob, name)),
+            Pattern::StackGlob(glob) => glob_match(glob, &functions.join(";")),
+        }
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Function(regex) => write!(f, "fn:{regex}"),
+            Pattern::Stack(regex) => write!(f, "stack:{regex}"),
+            Pattern::FunctionGlob(glob) => write!(f, "fn-glob:{glob}"),
+            Pattern::StackGlob(glob) => write!(f, "stack-glob:{glob}"),
+        }
+    }
+}
+
+/// Patterns of leaks to hide, one per line as `fn:<regex>` or `stack:<regex>`, which match
+/// anywhere in the name unless anchored with `^` and `$`. `fn-glob:<glob>` and
+/// `stack-glob:<glob>` match the whole name instead, where `*` matches any text and `?` a single
+/// character. Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+    pub patterns: Vec<Pattern>,
+}
+
+impl Suppressions {
+    pub fn defaults() -> Self {
+        static DEFAULTS: OnceLock<Suppressions> = OnceLock::new();
+
+        DEFAULTS
+            .get_or_init(|| Self::parse(DEFAULT_PATTERNS).expect("invalid default suppressions"))
+            .clone()
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut patterns = Vec::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let regex = |pattern: &str| {
+                Regex::new(pattern.trim()).map_err(|err| format!("line {}: {err}", n + 1))
+            };
+            let pattern = match line.split_once(':') {
+                Some(("fn", pattern)) => Pattern::Function(regex(pattern)?),
+                Some(("stack", pattern)) => Pattern::Stack(regex(pattern)?),
+                Some(("fn-glob", glob)) => Pattern::FunctionGlob(glob.trim().to_string()),
+                Some(("stack-glob", glob)) => Pattern::StackGlob(glob.trim().to_string()),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected fn:<regex>, stack:<regex>, fn-glob:<glob> or stack-glob:<glob>, got '{line}'",
+                        n + 1
+                    ));
+                }
+            };
+            patterns.push(pattern);
+        }
+
+        Ok(Self { patterns })
+    }
+
+    /// Index of the first pattern matching a stack of function names, outermost first.
+    pub fn find(&self, functions: &[&str]) -> Option<usize> {
+        self.patterns
+            .iter()
+            .position(|pattern| pattern.matches(functions))
+    }
+}
+
+/// Matches `text` against a glob where `*` matches any text, including none, and `?` any
+/// single character.
+pub fn glob_match(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut g, mut t) = (0, 0);
+    // position of the last `*` and of the text it is matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, t));
+                g += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    g = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_backtracks() {
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(glob_match("*::push", "alloc::vec::Vec<T>::push"));
+        assert!(glob_match("a?c*", "abc"));
+        assert!(!glob_match("a*b", "abc"));
+        assert!(!glob_match("*ab", "aba"));
+        assert!(!glob_match("a?c", "ac"));
+    }
+
+    #[test]
+    fn glob_match_empty() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("", "a"));
+        assert!(!glob_match("?", ""));
+    }
+
+    /// Function names of a stack, outermost first.
+    fn suppressed_by_defaults(functions: &[&str]) -> bool {
+        Suppressions::defaults().find(functions).is_some()
+    }
+
+    #[test]
+    fn parse_skips_comments_and_blank_lines() {
+        let suppressions = Suppressions::parse(
+            "# globals\n\n  fn: ^app::init$  \nstack:^main;\nfn-glob:app::*\nstack-glob:main;*\n",
+        )
+        .expect("valid suppressions");
+        let patterns = suppressions
+            .patterns
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            patterns,
+            [
+                "fn:^app::init$",
+                "stack:^main;",
+                "fn-glob:app::*",
+                "stack-glob:main;*"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        let err = Suppressions::parse("fn:app::init\napp::init\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+
+        let err = Suppressions::parse("# unknown kind\nfunction:app::init").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+
+        // globs are not regexes
+        let err = Suppressions::parse("fn:app::init\nfn:*lazy_static*").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+    }
+
+    #[test]
+    fn regexes_match_anywhere_unless_anchored() {
+        let suppressions = Suppressions::parse("fn:Vec<.*>::push\nstack:^main;app::run$").unwrap();
+        assert_eq!(
+            suppressions.find(&["main", "alloc::vec::Vec<T,A>::push"]),
+            Some(0)
+        );
+        assert_eq!(suppressions.find(&["main", "app::run"]), Some(1));
+        assert_eq!(suppressions.find(&["main", "app::run", "app::parse"]), None);
+    }
+
+    #[test]
+    fn defaults_hide_thread_local_initialisation() {
+        assert!(suppressed_by_defaults(&[
+            "main",
+            "std::thread::local::LocalKey<T>::with",
+            "std::thread::local::LocalKey<T>::try_with",
+            "std::sys::thread_local::native::lazy::Storage<T,D>::initialize",
+            "app::CACHE::{{constant}}::{{closure}}",
+            "alloc::vec::Vec<T>::with_capacity",
+        ]));
+        assert!(suppressed_by_defaults(&[
+            "main",
+            "std::thread::local::fast::Key<T>::try_initialize",
+            "alloc::vec::Vec<T>::with_capacity",
+        ]));
+    }
+
+    #[test]
+    fn defaults_keep_leaks_under_local_key_with() {
+        assert!(!suppressed_by_defaults(&[
+            "main",
+            "std::thread::local::LocalKey<T>::with",
+            "std::thread::local::LocalKey<T>::try_with",
+            "app::run::{{closure}}",
+            "alloc::vec::Vec<T,A>::push",
+        ]));
+    }
+
+    #[test]
+    fn defaults_hide_one_time_globals() {
+        assert!(suppressed_by_defaults(&[
+            "main",
+            "app::config",
+            "std::sync::once_lock::OnceLock<T>::get_or_init",
+            "std::sync::once_lock::OnceLock<T>::initialize",
+            "std::sync::once::Once::call_once_force",
+            "std::sync::once_lock::OnceLock<T>::initialize::{{closure}}",
+            "app::config::{{closure}}",
+            "alloc::boxed::Box<T>::new",
+            "alloc::alloc::exchange_malloc",
+        ]));
+        assert!(suppressed_by_defaults(&[
+            "main",
+            "<app::CONFIG as core::ops::deref::Deref>::deref",
+            "lazy_static::lazy::Lazy<T>::get",
+            "std::sync::once::Once::call_once",
+            "app::CONFIG::{{closure}}",
+            "alloc::vec::Vec<T>::with_capacity",
+        ]));
+    }
+
+    #[test]
+    fn defaults_keep_leaked_boxes() {
+        // `Box::leak` does not allocate, the stack only shows where the box was created
+        assert!(!suppressed_by_defaults(&[
+            "main",
+            "app::leak_config",
+            "alloc::boxed::Box<T>::new",
+            "alloc::alloc::exchange_malloc",
+        ]));
+    }
+}
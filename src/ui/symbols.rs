@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+
+const STD_CRATES: [&str; 5] = ["std", "core", "alloc", "proc_macro", "test"];
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Origin {
+    User,
+    Std,
+    ThirdParty,
+}
+
+/// Splits a demangled symbol into its path segments, ignoring `::` inside generic arguments.
+///
+/// Qualified paths such as `<alloc::vec::Vec<T> as core::clone::Clone>::clone` are resolved
+/// to the path of the self type followed by the method name.
+pub fn path_segments(symbol: &str) -> Vec<&str> {
+    let symbol = symbol.trim();
+
+    if let Some(qualified) = symbol.strip_prefix('<')
+        && let Some(end) = find_closing_angle(qualified)
+    {
+        let qualifier = split_top_level(&qualified[..end], " as ");
+        let rest = qualified[end + 1..].trim_start_matches("::");
+
+        // `<T as Trait>::method` says nothing about `T`, the trait path is more telling
+        let mut segments = match qualifier.as_slice() {
+            [self_type, trait_path] if !self_type.contains("::") => path_segments(trait_path),
+            _ => path_segments(qualifier[0]),
+        };
+        if !rest.is_empty() {
+            segments.extend(split_top_level(rest, "::"));
+        }
+        return segments;
+    }
+
+    let mut segments = split_top_level(symbol, "::");
+    if segments.len() > 1 && segments.last().is_some_and(|s| is_hash(s)) {
+        segments.pop();
+    }
+    segments
+}
+
+/// Legacy mangling appends `::h` followed by 16 hex digits to every symbol.
+pub fn is_hash(segment: &str) -> bool {
+    segment.len() == 17
+        && segment.starts_with('h')
+        && segment[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Name of the crate a symbol belongs to, e.g. `alloc` for `alloc::vec::Vec<T>::push`.
+pub fn crate_name(symbol: &str) -> &str {
+    path_segments(symbol)
+        .first()
+        .map(|segment| strip_generics(segment))
+        .unwrap_or(symbol)
+}
+
+/// Module path of a symbol limited to `depth` segments, without the function name itself.
+pub fn module_path(symbol: &str, depth: usize) -> String {
+    let segments = path_segments(symbol);
+    let modules = segments.len().saturating_sub(1).max(1);
+
+    segments
+        .iter()
+        .take(modules.min(depth.max(1)))
+        .map(|segment| strip_generics(segment))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Tells apart user code, the standard library and third-party crates.
+///
+/// The source file is the most reliable hint: std sources live under `/rustc/<hash>/` and
+/// dependencies under the cargo registry. Without a file only std crates can be recognised.
+pub fn origin(symbol: &str, file_name: Option<&str>) -> Origin {
+    if let Some(file_name) = file_name.filter(|f| !f.is_empty()) {
+        if file_name.starts_with("/rustc/") {
+            return Origin::Std;
+        }
+        if file_name.contains("/.cargo/registry/") || file_name.contains("/.cargo/git/") {
+            return Origin::ThirdParty;
+        }
+        return Origin::User;
+    }
+
+    if STD_CRATES.contains(&crate_name(symbol)) {
+        Origin::Std
+    } else {
+        Origin::User
+    }
+}
+
+/// How function names are displayed, the full name stays available on hover.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolOptions {
+    pub demangle: bool,
+    pub strip_hashes: bool,
+    pub collapse_generics: bool,
+    pub trim_closures: bool,
+}
+
+impl Default for SymbolOptions {
+    fn default() -> Self {
+        Self {
+            demangle: true,
+            strip_hashes: true,
+            collapse_generics: true,
+            trim_closures: true,
+        }
+    }
+}
+
+/// Display form of a symbol, e.g. `<alloc::vec::Vec<…> as core::clone::Clone>::clone` for a
+/// legacy-mangled `Vec<T>::clone` instance.
+pub fn prettify(symbol: &str, options: SymbolOptions) -> Cow<'_, str> {
+    let mut name = Cow::Borrowed(symbol);
+
+    if options.demangle
+        && let Ok(demangled) = rustc_demangle::try_demangle(symbol)
+    {
+        name = Cow::Owned(demangled.to_string());
+    }
+    if options.strip_hashes
+        && let Some((path, hash)) = name.rsplit_once("::")
+        && is_hash(hash)
+    {
+        name = Cow::Owned(path.to_string());
+    }
+    if options.trim_closures && name.contains("{closure") {
+        name = Cow::Owned(trim_closures(&name));
+    }
+    if options.collapse_generics && name.contains('<') {
+        name = Cow::Owned(collapse_generics(&name));
+    }
+
+    name
+}
+
+/// Merges nested closures, `{{closure}}::{{closure}}` and `{closure#1}`, into one `{closure}`.
+fn trim_closures(symbol: &str) -> String {
+    let mut segments = split_top_level(symbol, "::")
+        .into_iter()
+        .map(|segment| {
+            if segment == "{{closure}}" || segment.starts_with("{closure#") {
+                "{closure}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>();
+    segments.dedup_by(|a, b| *a == "{closure}" && *b == "{closure}");
+
+    segments.join("::")
+}
+
+/// Replaces generic arguments with `<…>`, keeping both sides of a qualified path.
+fn collapse_generics(symbol: &str) -> String {
+    let mut collapsed = String::with_capacity(symbol.len());
+    let mut rest = symbol;
+
+    if let Some(qualified) = rest.strip_prefix('<')
+        && let Some(end) = find_closing_angle(qualified)
+    {
+        let qualifier = split_top_level(&qualified[..end], " as ")
+            .into_iter()
+            .map(collapse_generics)
+            .collect::<Vec<_>>();
+
+        collapsed.push('<');
+        collapsed.push_str(&qualifier.join(" as "));
+        collapsed.push('>');
+        rest = &qualified[end + 1..];
+    }
+
+    while let Some(start) = rest.find('<')
+        && let Some(end) = find_closing_angle(&rest[start + 1..])
+    {
+        collapsed.push_str(&rest[..start]);
+        collapsed.push_str("<…>");
+        rest = &rest[start + end + 2..];
+    }
+    collapsed.push_str(rest);
+
+    collapsed
+}
+
+pub fn strip_generics(segment: &str) -> &str {
+    match segment.find('<') {
+        Some(0) | None => segment,
+        Some(idx) => &segment[..idx],
+    }
+}
+
+fn split_top_level<'a>(s: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut segments = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    let bytes = s.as_bytes();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' if i > 0 && bytes[i - 1] == b'-' => {}
+            b'>' | b')' | b']' => depth -= 1,
+            _ if depth == 0 && bytes[i..].starts_with(separator.as_bytes()) => {
+                segments.push(&s[start..i]);
+                i += separator.len();
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    segments.push(&s[start..]);
+
+    segments
+}
+
+fn find_closing_angle(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut depth = 1;
+
+    for (i, b) in bytes.iter().enumerate() {
+        match b {
+            b'<' => depth += 1,
+            b'>' if i > 0 && bytes[i - 1] == b'-' => {}
+            b'>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: SymbolOptions = SymbolOptions {
+        demangle: false,
+        strip_hashes: false,
+        collapse_generics: false,
+        trim_closures: false,
+    };
+
+    fn pretty(symbol: &str) -> Cow<'_, str> {
+        prettify(symbol, SymbolOptions::default())
+    }
+
+    #[test]
+    fn prettify_strips_hashes() {
+        assert_eq!(pretty("app::parse::h0123456789abcdef"), "app::parse");
+        assert_eq!(pretty("_ZN3app5parse17h0123456789abcdefE"), "app::parse");
+        // only a trailing hash segment is stripped
+        assert_eq!(pretty("app::h0123::parse"), "app::h0123::parse");
+        assert_eq!(
+            prettify("app::parse::h0123456789abcdef", RAW),
+            "app::parse::h0123456789abcdef"
+        );
+    }
+
+    #[test]
+    fn prettify_collapses_generics() {
+        assert_eq!(
+            pretty("hashbrown::map::HashMap<K,V,S>::insert"),
+            "hashbrown::map::HashMap<…>::insert"
+        );
+        assert_eq!(
+            pretty("<alloc::vec::Vec<T> as core::clone::Clone>::clone"),
+            "<alloc::vec::Vec<…> as core::clone::Clone>::clone"
+        );
+        assert_eq!(
+            pretty("alloc::vec::Vec<fn() -> u8>::push"),
+            "alloc::vec::Vec<…>::push"
+        );
+        assert_eq!(
+            pretty("<T as alloc::borrow::ToOwned>::to_owned"),
+            "<T as alloc::borrow::ToOwned>::to_owned"
+        );
+    }
+
+    #[test]
+    fn prettify_trims_closures() {
+        assert_eq!(
+            pretty("app::main::{{closure}}::{{closure}}"),
+            "app::main::{closure}"
+        );
+        assert_eq!(
+            pretty("app::run::{closure#1}::{closure#0}::h0123456789abcdef"),
+            "app::run::{closure}"
+        );
+        assert_eq!(
+            pretty("app::run::{{closure}}::inner::{{closure}}"),
+            "app::run::{closure}::inner::{closure}"
+        );
+    }
+
+    #[test]
+    fn path_segments_of_qualified_paths() {
+        assert_eq!(
+            path_segments("<alloc::vec::Vec<T> as core::clone::Clone>::clone"),
+            ["alloc", "vec", "Vec<T>", "clone"]
+        );
+        assert_eq!(
+            path_segments("<T as alloc::borrow::ToOwned>::to_owned"),
+            ["alloc", "borrow", "ToOwned", "to_owned"]
+        );
+        assert_eq!(crate_name("core::ptr::drop_in_place<u8>"), "core");
+    }
+}
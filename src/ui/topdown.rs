@@ -1,87 +1,87 @@
-use crate::ui::MemInfo;
+use crate::ui::calltree::{CallTree, Cost, NodeId, ROOT, string};
+use crate::ui::code::{CodeLoader, FileAnnotations};
+use crate::ui::stack::{Navigation, StackRef, frame_location, trace_frames};
+use crate::ui::symbols;
+use crate::ui::widgets::tree::TreeView;
+use crate::ui::{MemInfo, Settings};
 use bytesize::ByteSize;
 use egui::*;
-use egui_extras::{Column, TableBuilder};
-use egui_ltreeview::{Action, NodeBuilder, TreeView, TreeViewBuilder};
-use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
-use std::fs;
-use std::ops::Deref;
-use std::rc::Rc;
+use memtrace_utils::parser::AccumulatedData;
+use std::collections::HashMap;
 
 const MIN_PANEL_WIDTH: f32 = 500.0;
 
-#[derive(Debug, Clone)]
-struct StackNode {
-    info: Rc<RefCell<StackInfo>>,
-    children: BTreeMap<String, StackNode>,
-}
-
-#[derive(Debug, Clone, Default)]
-struct StackInfo {
-    id: u32,
-    name: String,
-    file_name: String,
-    line_number: u32,
-    peaked: u64,
-    leaked: u64,
-    allocations: u64,
-    temporary: u64,
-}
-
 pub struct TopDown {
     panel_width: f32,
-    root_node: StackNode,
-    stack_info_by_id: HashMap<u32, Rc<RefCell<StackInfo>>>,
-    selected_stack_info_id: u32,
+    tree_view: TreeView,
+    annotations: FileAnnotations,
     code_loader: CodeLoader,
 }
 
 impl TopDown {
     pub fn new(info: &MemInfo) -> Self {
-        let (root_stack_dir, file_info_by_id) = make_stack_dirs(info);
-
         Self {
             panel_width: MIN_PANEL_WIDTH,
-            root_node: root_stack_dir,
-            stack_info_by_id: file_info_by_id,
-            selected_stack_info_id: 0,
+            tree_view: TreeView::new(ROOT),
+            annotations: make_file_annotations(&info.data),
             code_loader: CodeLoader::new(),
         }
     }
 
-    pub fn show(&mut self, ui: &mut Ui) {
+    /// Forgets the selected and expanded nodes when the tree is rebuilt, e.g. after collapsing
+    /// recursion.
+    pub fn reset(&mut self) {
+        self.tree_view = TreeView::new(ROOT);
+    }
+
+    /// Selects the node of the call stack and expands its parents.
+    pub fn reveal(&mut self, info: &MemInfo, tree: &CallTree, stack: StackRef) {
+        let id = tree.find(&stack.frames(&info.data));
+        self.tree_view.reveal(id, tree.ancestors(id));
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        tree: &CallTree,
+        settings: &mut Settings,
+    ) -> Option<Navigation> {
+        let mut navigation = None;
+        let symbol_options = settings.symbols;
         let available_height = ui.available_height();
         let max_width = ui.available_width() / 2.0;
-        let max_height = ui.available_height();
-
-        let style = ui.style();
-        let font_size = style.text_styles.get(&TextStyle::Body).unwrap().size;
 
         ui.horizontal(|ui| {
             ui.horizontal(|ui| {
-                let id = ui.make_persistent_id("left_panel");
-
-                let (_, actions) = TreeView::new(id)
-                    .max_width(self.panel_width)
-                    .max_height(available_height)
-                    .allow_multi_selection(false)
-                    .show(ui, |view| {
-                        self.show_node(view, &self.root_node);
-                    });
-
-                for action in actions {
-                    match action {
-                        Action::SetSelected(ids) => {
-                            assert_eq!(ids.len(), 1);
-                            let info = self.stack_info_by_id.get(&ids[0]).unwrap();
-                            self.selected_stack_info_id = info.borrow().id;
-                        }
-                        Action::Move(_) => {}
-                        Action::Drag(_) => {}
-                        Action::Activate(_) => {}
-                    }
-                }
+                ui.allocate_ui(vec2(self.panel_width, available_height), |ui| {
+                    self.tree_view.show(
+                        ui,
+                        |id| &tree.node(id).children,
+                        |id| {
+                            let name = symbols::prettify(tree.name(&info.data, id), symbol_options);
+                            let text = RichText::new(name.as_ref());
+                            if tree.node(id).inlined {
+                                text.italics().into()
+                            } else {
+                                text.into()
+                            }
+                        },
+                        |id| {
+                            let name = tree.name(&info.data, id);
+                            let full_name = (symbols::prettify(name, symbol_options) != name)
+                                .then(|| name.to_string());
+                            match (full_name, tree.node(id).inlined) {
+                                (Some(full_name), true) => {
+                                    Some(format!("{full_name}\ninlined into its caller"))
+                                }
+                                (None, true) => Some("inlined into its caller".to_string()),
+                                (full_name, false) => full_name,
+                            }
+                        },
+                        |ui, id| show_context_menu(ui, &info.data, tree, id, &mut navigation),
+                    );
+                });
             });
 
             let separator_response = ui
@@ -110,205 +110,116 @@ impl TopDown {
             }
 
             ui.vertical(|ui| {
-                let info = self
-                    .stack_info_by_id
-                    .get(&self.selected_stack_info_id)
-                    .unwrap();
+                let selected = self.tree_view.selected();
+                let node = tree.node(selected);
+                let file_name = node.site.file_name(&info.data);
+
+                if !file_name.is_empty() {
+                    let name = tree.name(&info.data, selected);
+                    ui.label(RichText::new(symbols::prettify(name, symbol_options)).strong())
+                        .on_hover_text(name);
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{}:{} ⬅ Peak: {}, Leaked: {} (x{})",
+                            file_name,
+                            node.site.line_number,
+                            ByteSize::b(node.cost.peaked),
+                            ByteSize::b(node.cost.leaked),
+                            node.cost.allocations,
+                        ));
+                        if ui.small_button("Open in editor").clicked() {
+                            navigation = Some(open_in_editor(&info.data, tree, selected));
+                        }
+                        if ui.small_button("Details").clicked() {
+                            navigation = Some(Navigation::Details(tree.stack(selected)));
+                        }
+                    });
+                    ui.separator();
+                }
 
-                let offset = (max_height / font_size) as u32;
-                self.code_loader.show(ui, info.borrow(), offset);
+                self.code_loader.show(
+                    ui,
+                    &mut settings.source_resolver,
+                    file_name,
+                    node.site.line_number,
+                    self.annotations.get(file_name),
+                );
             });
         });
-    }
 
-    fn show_node(&self, view: &mut TreeViewBuilder<u32>, node: &StackNode) {
-        if node.children.is_empty() {
-            view.leaf(node.info.borrow().id, &node.info.borrow().name);
-        } else {
-            view.node(
-                NodeBuilder::dir(node.info.borrow().id)
-                    .label(&node.info.borrow().name)
-                    .default_open(false)
-                    .activatable(true),
-            );
-            for child in node.children.values() {
-                self.show_node(view, child);
-            }
-            view.close_dir()
-        }
+        navigation
     }
 }
 
-fn make_stack_dirs(info: &MemInfo) -> (StackNode, HashMap<u32, Rc<RefCell<StackInfo>>>) {
-    let mut global_id = 0;
-    let mut mapped = HashMap::new();
-
-    let root_info = Rc::new(RefCell::new(StackInfo {
-        name: "all".to_string(),
-        ..Default::default()
-    }));
-
-    let mut root = StackNode {
-        info: root_info.clone(),
-        children: BTreeMap::new(),
-    };
-    mapped.insert(global_id, root_info);
-
-    let mut ip_idxs = vec![];
-    for alloc_info in &info.data.allocation_infos {
-        let allocation = &info.data.allocations[alloc_info.allocation_idx as usize];
-
-        let mut trace_idx = allocation.trace_idx;
-        ip_idxs.clear();
-        while trace_idx != 0 {
-            let trace = &info.data.traces[trace_idx as usize - 1];
-            ip_idxs.push(trace.ip_idx);
-            trace_idx = trace.parent_idx;
+fn show_context_menu(
+    ui: &mut Ui,
+    data: &AccumulatedData,
+    tree: &CallTree,
+    id: NodeId,
+    navigation: &mut Option<Navigation>,
+) {
+    if id != ROOT {
+        if ui.button("Show details").clicked() {
+            *navigation = Some(Navigation::Details(tree.stack(id)));
+            ui.close_menu();
         }
-
-        let mut current = &mut root;
-
-        let mut parent_file_idx = 0;
-        let mut parent_ln = 0;
-        for ip_idx in ip_idxs.iter().rev() {
-            let ip_info = &info.data.instruction_pointers[*ip_idx as usize - 1];
-
-            for frame in ip_info.inlined.iter().chain(&ip_info.frame) {
-                let (fn_idx, file_idx, ln) = match frame {
-                    memtrace_utils::parser::Frame::Single { function_idx } => {
-                        (function_idx, &0, &0)
-                    }
-                    memtrace_utils::parser::Frame::Multiple {
-                        function_idx,
-                        file_idx,
-                        line_number,
-                    } => (function_idx, file_idx, line_number),
-                };
-
-                let key = format!("{}:{}:{}", fn_idx, parent_file_idx, parent_ln);
-
-                let child = current.children.entry(key).or_insert_with(|| {
-                    let file_name = if parent_file_idx == 0 {
-                        String::new()
-                    } else {
-                        info.data.strings[parent_file_idx - 1].clone()
-                    };
-
-                    global_id += 1;
-
-                    let name = if *fn_idx > 0 && *fn_idx <= info.data.strings.len() {
-                        &info.data.strings[*fn_idx - 1]
-                    } else {
-                        ""
-                    }
-                    .to_string();
-
-                    let info = Rc::new(RefCell::new(StackInfo {
-                        id: global_id,
-                        name,
-                        file_name,
-                        line_number: parent_ln,
-                        ..Default::default()
-                    }));
-
-                    let node = StackNode {
-                        info: info.clone(),
-                        children: BTreeMap::new(),
-                    };
-
-                    mapped.insert(node.info.borrow().id, info);
-
-                    node
-                });
-
-                {
-                    let mut info = child.info.borrow_mut();
-                    info.peaked += allocation.data.peak;
-                    info.leaked += allocation.data.leaked;
-                    info.allocations += allocation.data.allocations;
-                    info.temporary += allocation.data.temporary;
-                }
-
-                parent_file_idx = *file_idx;
-                parent_ln = *ln;
-
-                current = child;
-            }
+        if ui.button("Show in Flamegraph").clicked() {
+            *navigation = Some(Navigation::Flamegraph(tree.stack(id)));
+            ui.close_menu();
+        }
+        if ui.button("Copy stack").clicked() {
+            ui.ctx().copy_text(tree.stack(id).to_text(data));
+            ui.close_menu();
         }
     }
-
-    (root, mapped)
+    let enabled = tree.node(id).site.file_idx > 0;
+    if ui
+        .add_enabled(enabled, Button::new("Open in editor"))
+        .clicked()
+    {
+        *navigation = Some(open_in_editor(data, tree, id));
+        ui.close_menu();
+    }
 }
 
-struct CodeLoader {
-    mapped: HashMap<String, String>,
+fn open_in_editor(data: &AccumulatedData, tree: &CallTree, id: NodeId) -> Navigation {
+    let site = tree.node(id).site;
+    Navigation::OpenInEditor {
+        file_name: site.file_name(data).to_string(),
+        line_number: site.line_number,
+    }
 }
 
-impl CodeLoader {
-    pub fn new() -> Self {
-        Self {
-            mapped: HashMap::new(),
+/// Sums the costs of all allocations by the source lines on their stack. An allocation counts
+/// once per line, even when recursion puts the line on its stack several times.
+fn make_file_annotations(data: &AccumulatedData) -> FileAnnotations {
+    let mut lines = HashMap::<(usize, u32), Cost>::new();
+    let mut trace_lines = Vec::new();
+
+    for allocation in &data.allocations {
+        trace_lines.clear();
+        trace_lines.extend(
+            trace_frames(data, allocation.trace_idx)
+                .map(frame_location)
+                .filter(|(_, file_idx, _)| *file_idx > 0)
+                .map(|(_, file_idx, line_number)| (file_idx, line_number)),
+        );
+        trace_lines.sort_unstable();
+        trace_lines.dedup();
+
+        let cost = Cost::of(allocation);
+        for line in &trace_lines {
+            lines.entry(*line).or_default().add(&cost);
         }
     }
 
-    pub fn show(&mut self, ui: &mut Ui, stack_info: impl Deref<Target = StackInfo>, offset: u32) {
-        if !self.mapped.contains_key(&stack_info.file_name) {
-            let Ok(code) = fs::read_to_string(&stack_info.file_name) else {
-                return;
-            };
-            self.mapped.insert(stack_info.file_name.to_string(), code);
-        };
-
-        let code = self.mapped.get(&stack_info.file_name).unwrap();
-
-        let min_offset = stack_info.line_number.saturating_sub(offset / 2) as usize;
-        let max_offset = (stack_info.line_number + offset / 2) as usize;
-
-        let lines = code
-            .lines()
-            .enumerate()
-            .filter(|(i, _)| *i + 1 >= min_offset && *i + 1 <= max_offset);
-
-        TableBuilder::new(ui)
-            .resizable(false)
-            .cell_layout(Layout::left_to_right(Align::Center))
-            .column(Column::initial(20.0))
-            .column(Column::initial(100.0).at_most(120.0))
-            .column(Column::remainder())
-            .body(|mut body| {
-                for (i, line) in lines {
-                    let number = (i + 1) as u32;
-                    let is_target_line = number == stack_info.line_number;
-
-                    body.row(15.0, |mut row| {
-                        row.col(|ui| {
-                            ui.label(format!("{}", number));
-                        });
-
-                        row.col(|ui| {
-                            ui.label(line);
-                        });
-
-                        if is_target_line {
-                            row.col(|ui| {
-                                ui.label(
-                                    RichText::new(format!(
-                                        "⬅ Peak: {}, Leaked: {}",
-                                        ByteSize::b(stack_info.peaked).to_string(),
-                                        ByteSize::b(stack_info.leaked).to_string(),
-                                    ))
-                                    .strong(),
-                                );
-                                if stack_info.allocations > 1 {
-                                    ui.label(
-                                        RichText::new(format!("(x{})", stack_info.allocations))
-                                            .strong(),
-                                    );
-                                }
-                            });
-                        }
-                    });
-                }
-            });
+    let mut annotations = FileAnnotations::new();
+    for ((file_idx, line_number), cost) in lines {
+        annotations
+            .entry(string(data, file_idx).to_string())
+            .or_default()
+            .insert(line_number, cost);
     }
+
+    annotations
 }
@@ -0,0 +1,321 @@
+use crate::ui::calltree::{CallTree, Cost, NodeId, ROOT};
+use crate::ui::crates::{Grouping, group_allocations};
+use crate::ui::flamegraph::MemoryKind;
+use crate::ui::stack::Navigation;
+use crate::ui::symbols;
+use crate::ui::widgets::flamegraph::hashed_color;
+use crate::ui::widgets::treemap::{Treemap, TreemapItem};
+use crate::ui::{MemInfo, Settings};
+use egui::{ComboBox, RichText, Ui};
+use std::collections::BTreeMap;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Hierarchy {
+    CallTree,
+    Modules,
+}
+
+#[derive(Default)]
+struct ModuleNode {
+    cost: Cost,
+    children: BTreeMap<String, ModuleNode>,
+}
+
+/// Nested rectangles of the call tree or of the crate and module hierarchy, two levels at a
+/// time, drilling down on click.
+pub struct TreemapPage {
+    hierarchy: Hierarchy,
+    metric: MemoryKind,
+    /// Node of the call tree whose callees are shown.
+    call_root: NodeId,
+    /// Path of the module whose submodules are shown.
+    module_root: Vec<String>,
+    modules: Option<ModuleNode>,
+    context_node: Option<NodeId>,
+}
+
+impl TreemapPage {
+    pub fn new() -> Self {
+        Self {
+            hierarchy: Hierarchy::CallTree,
+            metric: MemoryKind::Peak,
+            call_root: ROOT,
+            module_root: Vec::new(),
+            modules: None,
+            context_node: None,
+        }
+    }
+
+    /// Goes back to the top level, e.g. after the call tree was rebuilt.
+    pub fn reset(&mut self) {
+        self.call_root = ROOT;
+        self.module_root.clear();
+        self.context_node = None;
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        tree: &CallTree,
+        settings: &Settings,
+    ) -> Option<Navigation> {
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Hierarchy")
+                .selected_text(match self.hierarchy {
+                    Hierarchy::CallTree => "Call tree",
+                    Hierarchy::Modules => "Crates and modules",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.hierarchy, Hierarchy::CallTree, "Call tree");
+                    ui.selectable_value(
+                        &mut self.hierarchy,
+                        Hierarchy::Modules,
+                        "Crates and modules",
+                    );
+                });
+
+            ComboBox::from_label("Size")
+                .selected_text(format!("{:?}", self.metric))
+                .show_ui(ui, |ui| {
+                    for kind in MemoryKind::ALL {
+                        ui.selectable_value(&mut self.metric, kind, format!("{:?}", kind));
+                    }
+                });
+        });
+
+        match self.hierarchy {
+            Hierarchy::CallTree => self.show_call_tree(ui, info, tree, settings),
+            Hierarchy::Modules => {
+                self.show_modules(ui, info);
+                None
+            }
+        }
+    }
+
+    fn show_call_tree(
+        &mut self,
+        ui: &mut Ui,
+        info: &MemInfo,
+        tree: &CallTree,
+        settings: &Settings,
+    ) -> Option<Navigation> {
+        let name = |id: NodeId| symbols::prettify(tree.name(&info.data, id), settings.symbols);
+
+        ui.horizontal_wrapped(|ui| {
+            let mut path = tree.ancestors(self.call_root);
+            path.push(self.call_root);
+            for (i, id) in path.into_iter().enumerate() {
+                if i > 0 {
+                    ui.label("›");
+                }
+                if ui
+                    .selectable_label(id == self.call_root, name(id))
+                    .clicked()
+                {
+                    self.call_root = id;
+                }
+            }
+        });
+        ui.separator();
+
+        let metric = self.metric;
+        let item = |id: NodeId, children: Vec<TreemapItem>| {
+            let label = name(id).into_owned();
+            TreemapItem {
+                color: hashed_color(symbols::crate_name(&label)),
+                label,
+                value: metric.cost_value(&tree.node(id).cost),
+                children,
+            }
+        };
+        let children = &tree.node(self.call_root).children;
+        let items = children
+            .iter()
+            .map(|child| {
+                let grandchildren = tree.node(*child).children.iter();
+                item(
+                    *child,
+                    grandchildren.map(|id| item(*id, Vec::new())).collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let root_value = metric.cost_value(&tree.node(self.call_root).cost);
+        let format_value = |value: f64| metric.format(value);
+        let response = Treemap::new(&items, &format_value).show(ui);
+
+        // items follow the order of the children in the tree
+        let call_root = self.call_root;
+        let node_at = |path: &[usize]| {
+            path.iter()
+                .fold(call_root, |id, idx| tree.node(id).children[*idx])
+        };
+
+        if let Some(path) = &response.clicked {
+            let id = node_at(path);
+            self.call_root = if tree.node(id).children.is_empty() {
+                tree.node(id).parent
+            } else {
+                id
+            };
+        }
+
+        if response.response.secondary_clicked() {
+            self.context_node = response.hovered.as_deref().map(node_at);
+        }
+
+        let mut navigation = None;
+        response.response.context_menu(|ui| {
+            let Some(id) = self.context_node else {
+                ui.close_menu();
+                return;
+            };
+            if ui.button("Show details").clicked() {
+                navigation = Some(Navigation::Details(tree.stack(id)));
+                ui.close_menu();
+            }
+            if ui.button("Show in TopDown").clicked() {
+                navigation = Some(Navigation::TopDown(tree.stack(id)));
+                ui.close_menu();
+            }
+            if ui.button("Show in Flamegraph").clicked() {
+                navigation = Some(Navigation::Flamegraph(tree.stack(id)));
+                ui.close_menu();
+            }
+        });
+
+        if let Some(path) = &response.hovered
+            && !response.response.context_menu_opened()
+        {
+            let id = node_at(path);
+            response.response.on_hover_ui_at_pointer(|ui| {
+                ui.label(RichText::new(tree.name(&info.data, id)).strong());
+                show_value(
+                    ui,
+                    metric,
+                    metric.cost_value(&tree.node(id).cost),
+                    root_value,
+                );
+            });
+        }
+
+        navigation
+    }
+
+    fn show_modules(&mut self, ui: &mut Ui, info: &MemInfo) {
+        let modules = self.modules.get_or_insert_with(|| make_module_tree(info));
+
+        ui.horizontal_wrapped(|ui| {
+            if ui
+                .selectable_label(self.module_root.is_empty(), "all")
+                .clicked()
+            {
+                self.module_root.clear();
+            }
+            for i in 0..self.module_root.len() {
+                ui.label("›");
+                let last = i + 1 == self.module_root.len();
+                if ui.selectable_label(last, &self.module_root[i]).clicked() {
+                    self.module_root.truncate(i + 1);
+                    break;
+                }
+            }
+        });
+        ui.separator();
+
+        let mut root = &*modules;
+        for name in &self.module_root {
+            match root.children.get(name) {
+                Some(child) => root = child,
+                None => break,
+            }
+        }
+
+        let metric = self.metric;
+        let item = |name: &str, node: &ModuleNode, children: Vec<TreemapItem>| TreemapItem {
+            label: name.to_string(),
+            value: metric.cost_value(&node.cost),
+            color: hashed_color(name),
+            children,
+        };
+        let items = root
+            .children
+            .iter()
+            .map(|(name, node)| {
+                let children = node.children.iter();
+                item(
+                    name,
+                    node,
+                    children
+                        .map(|(name, node)| item(name, node, Vec::new()))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let root_value = metric.cost_value(&root.cost);
+        let format_value = |value: f64| metric.format(value);
+        let response = Treemap::new(&items, &format_value).show(ui);
+
+        let names_at = |path: &[usize]| {
+            let mut node = root;
+            let mut names = Vec::new();
+            for idx in path {
+                let (name, child) = node.children.iter().nth(*idx).unwrap();
+                names.push(name.clone());
+                node = child;
+            }
+            (names, node)
+        };
+
+        if let Some(path) = &response.hovered {
+            let (names, node) = names_at(path);
+            response.response.on_hover_ui_at_pointer(|ui| {
+                let mut full_path = self.module_root.clone();
+                full_path.extend(names);
+                ui.label(RichText::new(full_path.join("::")).strong());
+                show_value(ui, metric, metric.cost_value(&node.cost), root_value);
+            });
+        }
+
+        if let Some(path) = &response.clicked {
+            let (mut names, node) = names_at(path);
+            if node.children.is_empty() {
+                names.pop();
+            }
+            self.module_root.extend(names);
+        }
+    }
+}
+
+fn show_value(ui: &mut Ui, metric: MemoryKind, value: f64, root_value: f64) {
+    let share = if root_value > 0.0 {
+        value / root_value * 100.0
+    } else {
+        0.0
+    };
+    ui.label(format!(
+        "{:?}: {} ({:.2}%)",
+        metric,
+        metric.format(value),
+        share
+    ));
+}
+
+/// Crates and their modules down to the full module path of every allocation's owner.
+fn make_module_tree(info: &MemInfo) -> ModuleNode {
+    let mut root = ModuleNode::default();
+
+    for group in group_allocations(&info.data, Grouping::Module, usize::MAX) {
+        root.cost.add(&group.cost);
+
+        let mut node = &mut root;
+        for segment in group.name.split("::") {
+            node = node.children.entry(segment.to_string()).or_default();
+            node.cost.add(&group.cost);
+        }
+    }
+
+    root
+}
@@ -1,6 +1,7 @@
 use eframe::egui::*;
 use egui::ecolor::Hsva;
-use std::collections::{BTreeMap, HashSet};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 
 const FRAME_V_SPACING: f32 = 4.0;
@@ -8,90 +9,278 @@ const FRAME_H_SPACING: f32 = 4.0;
 const INFO_BAR_HEIGHT: f32 = 35.0;
 const TEXT_HEIGHT: f32 = 15.0;
 
+/// Frames narrower than this are not drawn, nor their children.
+const MIN_FRAME_WIDTH: f32 = 0.5;
+
 #[derive(Clone)]
 pub struct Options {
     pub frame_height: f32,
 }
 
-#[derive(Clone, Default)]
-struct StackFrame {
-    chain_ids: HashSet<u32>,
+/// Frames of a flamegraph as a tree whose root is node 0. The value of a node includes the
+/// values of its children.
+pub trait FlameTree {
+    fn children(&self, id: u32) -> &[u32];
+    fn parent(&self, id: u32) -> u32;
+    fn value(&self, id: u32) -> f64;
+    fn label(&self, id: u32) -> Cow<'_, str>;
+    /// Number of levels below the root.
+    fn max_depth(&self) -> u32;
+}
+
+struct FoldedNode {
     label: String,
     value: f64,
-    children: BTreeMap<String, StackFrame>,
+    parent: u32,
+    children: BTreeMap<String, u32>,
+    /// `children` in the order of their labels.
+    sorted: Vec<u32>,
+}
+
+/// Tree of the stacks of a folded file, built once rather than on every repaint.
+pub struct FoldedTree {
+    nodes: Vec<FoldedNode>,
+    max_depth: u32,
+}
+
+impl FoldedTree {
+    /// Builds the tree of `frame;frame;frame value` lines, skipping malformed ones. With
+    /// `collapse_recursion` recursive frames merge into their first occurrence on the stack.
+    pub fn new<'a>(lines: impl IntoIterator<Item = &'a str>, collapse_recursion: bool) -> Self {
+        let mut tree = Self {
+            nodes: vec![FoldedNode {
+                label: "all".to_string(),
+                value: 0.0,
+                parent: 0,
+                children: BTreeMap::new(),
+                sorted: Vec::new(),
+            }],
+            max_depth: 0,
+        };
+
+        let mut path = Vec::new();
+        for line in lines {
+            let Some((frames, value)) = line.rsplit_once(' ') else {
+                continue;
+            };
+            let Ok(value) = value.parse::<f64>() else {
+                continue;
+            };
+
+            path.clear();
+            for frame in frames.split(';') {
+                if collapse_recursion {
+                    merge_recursion(&mut path, frame);
+                } else {
+                    path.push(frame);
+                }
+            }
+            tree.add(&path, value);
+        }
+
+        for node in &mut tree.nodes {
+            node.sorted = node.children.values().copied().collect();
+        }
+        tree
+    }
+
+    /// Adds a chain below the root, every frame of it including the leaf getting its own node.
+    fn add(&mut self, frames: &[&str], value: f64) {
+        let mut id = 0;
+        self.nodes[0].value += value;
+        self.max_depth = self.max_depth.max(frames.len() as u32);
+
+        for frame in frames {
+            let next = match self.nodes[id as usize].children.get(*frame) {
+                Some(child) => *child,
+                None => {
+                    let child = self.nodes.len() as u32;
+                    self.nodes.push(FoldedNode {
+                        label: frame.to_string(),
+                        value: 0.0,
+                        parent: id,
+                        children: BTreeMap::new(),
+                        sorted: Vec::new(),
+                    });
+                    self.nodes[id as usize]
+                        .children
+                        .insert(frame.to_string(), child);
+                    child
+                }
+            };
+            self.nodes[next as usize].value += value;
+            id = next;
+        }
+    }
+}
+
+impl FlameTree for FoldedTree {
+    fn children(&self, id: u32) -> &[u32] {
+        &self.nodes[id as usize].sorted
+    }
+
+    fn parent(&self, id: u32) -> u32 {
+        self.nodes[id as usize].parent
+    }
+
+    fn value(&self, id: u32) -> f64 {
+        self.nodes[id as usize].value
+    }
+
+    fn label(&self, id: u32) -> Cow<'_, str> {
+        Cow::Borrowed(&self.nodes[id as usize].label)
+    }
+
+    fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
 }
 
-struct Canvas {
+struct Canvas<'a> {
     response: Response,
     rect: Rect,
     painter: Painter,
+    root_value: f64,
+    max_depth: u32,
+    /// Nodes from the root down to the selected one, which are drawn across the whole width.
+    zoom_path: Vec<u32>,
+    format_value: &'a dyn Fn(f64) -> String,
+    format_label: &'a dyn Fn(&str) -> String,
+    color_of: &'a dyn Fn(&str, f64) -> Color32,
+}
+
+/// The frame under the mouse pointer.
+#[derive(Clone)]
+pub struct HoveredFrame {
+    pub id: u32,
+    pub label: String,
+    pub value: f64,
+    pub parent_value: f64,
+    pub root_value: f64,
+}
+
+pub struct FlamegraphResponse {
+    pub response: Response,
+    pub hovered: Option<HoveredFrame>,
 }
 
 pub struct Flamegraph {
     options: Options,
-    selected_chain_ids: Option<HashSet<u32>>,
+    selected: Option<u32>,
     info_bar_text: String,
+    hovered: Option<HoveredFrame>,
+    clicked: Option<u32>,
 }
 
 impl Flamegraph {
-    pub fn new<'a>(opts: Options) -> Self {
+    pub fn new(opts: Options) -> Self {
         Self {
             options: opts,
-            selected_chain_ids: None,
+            selected: None,
             info_bar_text: String::new(),
+            hovered: None,
+            clicked: None,
         }
     }
 
-    pub fn show<'a>(&mut self, ui: &mut Ui, frames: impl IntoIterator<Item = &'a str>, unit: &str) {
-        ui.horizontal_centered(|ui| {
-            let (root, max_depth) = build_stackframes(frames);
-
-            Frame::canvas(ui.style()).show(ui, |ui| {
-                let rect = ui.available_rect_before_wrap();
-                let response = ui.interact(rect, ui.id().with("canvas"), Sense::click_and_drag());
+    /// Zooms into a node of the tree shown next.
+    pub fn focus(&mut self, id: u32) {
+        self.selected = Some(id);
+    }
 
-                let canvas = Canvas {
-                    response,
-                    rect,
-                    painter: ui.painter_at(rect),
-                };
+    /// Draws the frames, `format_label` shortens the labels drawn on the frames and `color_of`
+    /// picks the colour of a frame from its label and its share of the root value.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        tree: &impl FlameTree,
+        format_value: impl Fn(f64) -> String,
+        format_label: impl Fn(&str) -> String,
+        color_of: impl Fn(&str, f64) -> Color32,
+    ) -> FlamegraphResponse {
+        self.hovered = None;
+        self.clicked = None;
+
+        let mut zoom_path = Vec::new();
+        if let Some(mut id) = self.selected {
+            while id != 0 {
+                zoom_path.push(id);
+                id = tree.parent(id);
+            }
+            zoom_path.push(0);
+            zoom_path.reverse();
+        }
 
-                self.draw(&canvas, &root, max_depth, root.value, unit);
-            });
+        let response = ui.horizontal_centered(|ui| {
+            Frame::canvas(ui.style())
+                .show(ui, |ui| {
+                    let rect = ui.available_rect_before_wrap();
+                    let response =
+                        ui.interact(rect, ui.id().with("canvas"), Sense::click_and_drag());
+
+                    let canvas = Canvas {
+                        response,
+                        rect,
+                        painter: ui.painter_at(rect),
+                        root_value: tree.value(0),
+                        max_depth: tree.max_depth(),
+                        zoom_path,
+                        format_value: &format_value,
+                        format_label: &format_label,
+                        color_of: &color_of,
+                    };
+
+                    self.draw(&canvas, tree);
+
+                    canvas.response
+                })
+                .inner
         });
+
+        if self.clicked.is_some() {
+            self.selected = self.clicked;
+        }
+
+        FlamegraphResponse {
+            response: response.inner,
+            hovered: self.hovered.take(),
+        }
     }
 
     pub fn reset(&mut self) {
-        self.selected_chain_ids = None;
+        self.selected = None;
         self.info_bar_text.clear();
     }
 
-    fn draw(
-        &mut self,
-        canvas: &Canvas,
-        root: &StackFrame,
-        max_depth: u32,
-        root_value: f64,
-        unit: &str,
-    ) {
+    fn draw(&mut self, canvas: &Canvas, tree: &impl FlameTree) {
         let min_x = canvas.rect.min.x;
         let max_x = canvas.rect.max.x;
 
-        self.draw_one_frame(canvas, root, max_depth, min_x, max_x, root_value, unit);
+        self.draw_one_frame(
+            canvas,
+            tree,
+            0,
+            0,
+            canvas.root_value,
+            Rangef::new(min_x, max_x),
+        );
 
-        self.draw_info_bar(canvas, max_depth, min_x, max_x);
+        self.draw_info_bar(canvas, canvas.max_depth, min_x, max_x);
     }
 
     fn draw_one_frame(
         &mut self,
         canvas: &Canvas,
-        frame: &StackFrame,
-        depth: u32,
-        min_x: f32,
-        max_x: f32,
-        root_value: f64,
-        unit: &str,
+        tree: &impl FlameTree,
+        id: u32,
+        level: u32,
+        parent_value: f64,
+        x_range: Rangef,
     ) {
+        let (min_x, max_x) = (x_range.min, x_range.max);
+        let value = tree.value(id);
+        let label = tree.label(id);
+        let depth = canvas.max_depth.saturating_sub(level);
         let min_y =
             canvas.rect.min.y + depth as f32 * (self.options.frame_height + FRAME_V_SPACING);
 
@@ -105,27 +294,38 @@ impl Flamegraph {
             false
         };
 
-        let mut rect_color = make_frame_color(frame.value, depth, min_x, max_x);
+        let mut rect_color = (canvas.color_of)(&label, value / canvas.root_value);
 
         if is_hovered {
             rect_color = saturate(rect_color, 0.3);
 
             self.info_bar_text = format!(
-                "{} ({} {},  {:.2}%)",
-                frame.label,
-                frame.value,
-                unit,
-                (frame.value / root_value) * 100.0
+                "{} ({},  {:.2}%)",
+                (canvas.format_label)(&label),
+                (canvas.format_value)(value),
+                (value / canvas.root_value) * 100.0
             );
 
+            self.hovered = Some(HoveredFrame {
+                id,
+                label: label.to_string(),
+                value,
+                parent_value,
+                root_value: canvas.root_value,
+            });
+
             if canvas.response.clicked() {
-                self.selected_chain_ids = Some(frame.chain_ids.clone());
+                self.clicked = Some(id);
             }
         };
 
         canvas.painter.rect_filled(rect, 0.0, rect_color);
         let painter = canvas.painter.with_clip_rect(rect.intersect(canvas.rect));
-        let text = format!("{}: {}", frame.label, frame.value);
+        let text = format!(
+            "{}: {}",
+            (canvas.format_label)(&label),
+            (canvas.format_value)(value)
+        );
 
         let text_pos = pos2(
             min_x + 4.0,
@@ -140,39 +340,42 @@ impl Flamegraph {
             Color32::BLACK,
         );
 
+        if value <= 0.0 {
+            return;
+        }
+
+        // on the way to the selected frame only the next frame of the path is drawn
+        let zoomed = match canvas.zoom_path.get(level as usize) {
+            Some(on_path) if *on_path == id => canvas.zoom_path.get(level as usize + 1),
+            _ => None,
+        };
+
         let mut child_min_x = min_x;
         let length = max_x - min_x;
-        for (_, child) in &frame.children {
-            if child.value == 0.0 {
+        for &child in tree.children(id) {
+            let child_value = tree.value(child);
+            if child_value == 0.0 {
                 continue;
             }
 
-            let is_selected = if let Some(selected) = self.selected_chain_ids.as_ref() {
-                if selected.intersection(&child.chain_ids).next().is_none() {
-                    continue;
-                }
-                selected == &child.chain_ids
-            } else {
-                false
+            let child_value = match zoomed {
+                Some(zoomed) if *zoomed == child => value,
+                Some(_) => continue,
+                None => value.min(child_value),
             };
 
-            let child_value = if is_selected {
-                frame.value
-            } else {
-                frame.value.min(child.value)
-            };
-
-            let child_max_x = max_x.min(child_min_x + (child_value / frame.value) as f32 * length);
-
-            self.draw_one_frame(
-                canvas,
-                child,
-                depth - 1,
-                child_min_x,
-                child_max_x,
-                root_value,
-                unit,
-            );
+            let child_max_x = max_x.min(child_min_x + (child_value / value) as f32 * length);
+
+            if child_max_x - child_min_x >= MIN_FRAME_WIDTH {
+                self.draw_one_frame(
+                    canvas,
+                    tree,
+                    child,
+                    level + 1,
+                    value,
+                    Rangef::new(child_min_x, child_max_x),
+                );
+            }
 
             child_min_x = child_max_x + FRAME_H_SPACING;
         }
@@ -198,57 +401,19 @@ impl Flamegraph {
     }
 }
 
-fn build_stackframes<'a>(chains: impl IntoIterator<Item = &'a str>) -> (StackFrame, u32) {
-    let mut root = StackFrame::default();
-    root.label = "all".to_string();
-
-    let mut max_depth = 0;
-    for (chain_id, chain) in chains.into_iter().enumerate() {
-        let (frames, value) = chain.rsplit_once(" ").unwrap();
-        let value = value.parse::<f64>().unwrap();
-
-        root.chain_ids.insert(chain_id as u32);
-        root.value += value;
-        fill_children(&mut root, frames, value, chain_id as u32, 1, &mut max_depth);
+/// Appends a frame to a path, or returns to the frame's first occurrence when it is already on
+/// the path, which merges direct and indirect recursion.
+pub fn merge_recursion<T: PartialEq>(path: &mut Vec<T>, frame: T) {
+    match path.iter().position(|f| *f == frame) {
+        Some(idx) => path.truncate(idx + 1),
+        None => path.push(frame),
     }
-
-    (root, max_depth)
-}
-
-fn fill_children(
-    sf: &mut StackFrame,
-    frames: &str,
-    value: f64,
-    chain_id: u32,
-    depth: u32,
-    max_depth: &mut u32,
-) {
-    let Some((frame, frames)) = frames.split_once(";") else {
-        sf.label = frames.to_string();
-        return;
-    };
-
-    *max_depth = depth.max(*max_depth);
-
-    let next = sf
-        .children
-        .entry(frame.to_string())
-        .or_insert_with(|| StackFrame {
-            chain_ids: HashSet::new(),
-            label: frame.to_string(),
-            value: 0.0,
-            children: Default::default(),
-        });
-
-    next.chain_ids.insert(chain_id);
-    next.value += value;
-
-    fill_children(next, frames, value, chain_id, depth + 1, max_depth);
 }
 
-pub fn make_frame_color(value: f64, depth: u32, min_x: f32, max_x: f32) -> Color32 {
+/// Stable colour for a key, so the same function keeps its colour across zooms and views.
+pub fn hashed_color(key: impl Hash) -> Color32 {
     let mut hasher = DefaultHasher::new();
-    (value.to_bits(), depth, min_x.to_bits(), max_x.to_bits()).hash(&mut hasher);
+    key.hash(&mut hasher);
     let hash = hasher.finish();
 
     let hue_variation = 0.3 + ((hash & 0xFF) as f32 / 255.0) * 0.7; // [0.3, 1.0]
@@ -273,6 +438,20 @@ pub fn make_frame_color(value: f64, depth: u32, min_x: f32, max_x: f32) -> Color
     Color32::from(hsva)
 }
 
+/// Yellow to red scale for a share in `[0.0, 1.0]`.
+pub fn heat_color(share: f64) -> Color32 {
+    let share = share.clamp(0.0, 1.0) as f32;
+
+    let hsva = Hsva {
+        h: (1.0 - share) * 60.0 / 360.0,
+        s: 0.5 + share * 0.4,
+        v: 0.95,
+        a: 1.0,
+    };
+
+    Color32::from(hsva)
+}
+
 fn saturate(color: Color32, factor: f32) -> Color32 {
     let mut hsv = Hsva::from(color);
     hsv.s = (hsv.s * (1.0 + factor)).clamp(0.0, 1.0);
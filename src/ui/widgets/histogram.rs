@@ -0,0 +1,89 @@
+use eframe::egui::*;
+
+const LABEL_HEIGHT: f32 = 18.0;
+const BAR_SPACING: f32 = 2.0;
+
+pub struct Bar {
+    pub label: String,
+    pub value: f64,
+}
+
+pub struct HistogramResponse {
+    pub response: Response,
+    pub hovered: Option<usize>,
+    pub clicked: Option<usize>,
+}
+
+/// Vertical bars with their labels below, one of which can be selected.
+pub struct Histogram<'a> {
+    bars: &'a [Bar],
+    selected: Option<usize>,
+    height: f32,
+}
+
+impl<'a> Histogram<'a> {
+    pub fn new(bars: &'a [Bar], selected: Option<usize>, height: f32) -> Self {
+        Self {
+            bars,
+            selected,
+            height,
+        }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> HistogramResponse {
+        let size = vec2(ui.available_width(), self.height);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+
+        let max = self.bars.iter().map(|bar| bar.value).fold(0.0, f64::max);
+        let bar_width = rect.width() / self.bars.len().max(1) as f32;
+        let plot_height = rect.height() - LABEL_HEIGHT;
+        let visuals = ui.visuals();
+
+        let mut hovered = None;
+        for (idx, bar) in self.bars.iter().enumerate() {
+            let x = rect.min.x + idx as f32 * bar_width;
+            let column = Rect::from_min_size(pos2(x, rect.min.y), vec2(bar_width, rect.height()));
+            let is_hovered = response.hover_pos().is_some_and(|pos| column.contains(pos));
+            if is_hovered {
+                hovered = Some(idx);
+            }
+
+            if is_hovered || self.selected == Some(idx) {
+                painter.rect_filled(column, 2.0, visuals.faint_bg_color);
+            }
+
+            let height = if max > 0.0 {
+                (bar.value / max) as f32 * plot_height
+            } else {
+                0.0
+            };
+            let bar_rect = Rect::from_min_max(
+                pos2(x + BAR_SPACING, rect.min.y + plot_height - height),
+                pos2(x + bar_width - BAR_SPACING, rect.min.y + plot_height),
+            );
+            let color = if self.selected == Some(idx) {
+                visuals.selection.bg_fill
+            } else {
+                visuals.widgets.inactive.bg_fill
+            };
+            painter.rect_filled(bar_rect, 2.0, color);
+
+            painter.text(
+                pos2(x + bar_width / 2.0, rect.max.y - LABEL_HEIGHT / 2.0),
+                Align2::CENTER_CENTER,
+                &bar.label,
+                FontId::proportional(11.0),
+                visuals.text_color(),
+            );
+        }
+
+        let clicked = if response.clicked() { hovered } else { None };
+
+        HistogramResponse {
+            response,
+            hovered,
+            clicked,
+        }
+    }
+}
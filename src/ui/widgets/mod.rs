@@ -1 +1,4 @@
 pub mod flamegraph;
+pub mod histogram;
+pub mod tree;
+pub mod treemap;
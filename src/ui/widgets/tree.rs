@@ -0,0 +1,170 @@
+use eframe::egui::*;
+use std::collections::HashSet;
+use std::ops::Range;
+
+const INDENT: f32 = 14.0;
+
+#[derive(Clone, Copy)]
+struct Row {
+    id: u32,
+    depth: usize,
+    has_children: bool,
+}
+
+/// A tree view that only lays out the visible rows, so that trees with hundreds of thousands
+/// of nodes stay responsive. Children are only visited once their parent is expanded.
+pub struct TreeView {
+    root: u32,
+    expanded: HashSet<u32>,
+    selected: u32,
+    rows: Vec<Row>,
+    dirty: bool,
+    /// Rows shown in the last frame, including the partially visible ones.
+    visible: Range<usize>,
+    scroll_to_selected: bool,
+}
+
+impl TreeView {
+    pub fn new(root: u32) -> Self {
+        Self {
+            root,
+            expanded: HashSet::new(),
+            selected: root,
+            rows: Vec::new(),
+            dirty: true,
+            visible: 0..0,
+            scroll_to_selected: false,
+        }
+    }
+
+    pub fn selected(&self) -> u32 {
+        self.selected
+    }
+
+    /// Selects a node, expanding its ancestors given from the root down, and scrolls to it.
+    pub fn reveal(&mut self, id: u32, ancestors: impl IntoIterator<Item = u32>) {
+        self.expanded.extend(ancestors);
+        self.selected = id;
+        self.dirty = true;
+        self.scroll_to_selected = true;
+    }
+
+    pub fn show<'a>(
+        &mut self,
+        ui: &mut Ui,
+        children: impl Fn(u32) -> &'a [u32],
+        label: impl Fn(u32) -> WidgetText,
+        hover_text: impl Fn(u32) -> Option<String>,
+        mut context_menu: impl FnMut(&mut Ui, u32),
+    ) {
+        if self.dirty {
+            self.rebuild_rows(&children);
+        }
+
+        let row_height = ui.spacing().interact_size.y;
+
+        let mut scroll_area = ScrollArea::both().id_salt("tree_view").auto_shrink(false);
+        if self.scroll_to_selected {
+            self.scroll_to_selected = false;
+            if let Some(index) = self.rows.iter().position(|row| row.id == self.selected)
+                && !(self.visible.start < index && index + 1 < self.visible.end)
+            {
+                let offset = index as f32 * (row_height + ui.spacing().item_spacing.y);
+                scroll_area = scroll_area
+                    .vertical_scroll_offset((offset - ui.available_height() / 2.0).max(0.0));
+            }
+        }
+
+        let mut toggled = None;
+        let response = scroll_area.show_rows(ui, row_height, self.rows.len(), |ui, range| {
+            ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+            self.visible = range.clone();
+
+            for row in &self.rows[range] {
+                ui.horizontal(|ui| {
+                    ui.add_space(row.depth as f32 * INDENT);
+
+                    if row.has_children {
+                        let open = self.expanded.contains(&row.id);
+                        let icon = if open { "⏷" } else { "⏵" };
+                        if ui.add(Button::new(icon).frame(false)).clicked() {
+                            toggled = Some(row.id);
+                        }
+                    } else {
+                        ui.add_space(ui.spacing().icon_width);
+                    }
+
+                    let mut response = ui.selectable_label(self.selected == row.id, label(row.id));
+                    if let Some(text) = hover_text(row.id) {
+                        response = response.on_hover_text_at_pointer(text);
+                    }
+                    if response.clicked() {
+                        self.selected = row.id;
+                    }
+                    if response.double_clicked() && row.has_children {
+                        toggled = Some(row.id);
+                    }
+                    response.context_menu(|ui| context_menu(ui, row.id));
+                });
+            }
+        });
+
+        if response
+            .inner_rect
+            .contains(ui.ctx().pointer_hover_pos().unwrap_or_default())
+        {
+            self.handle_keys(ui, &mut toggled);
+        }
+
+        if let Some(id) = toggled
+            && !self.expanded.remove(&id)
+        {
+            self.expanded.insert(id);
+        }
+        if toggled.is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Arrow keys move the selection, left and right collapse and expand it.
+    fn handle_keys(&mut self, ui: &Ui, toggled: &mut Option<u32>) {
+        let Some(index) = self.rows.iter().position(|row| row.id == self.selected) else {
+            return;
+        };
+        let row = self.rows[index];
+        let open = self.expanded.contains(&row.id);
+
+        ui.input(|input| {
+            if input.key_pressed(Key::ArrowDown) && index + 1 < self.rows.len() {
+                self.selected = self.rows[index + 1].id;
+                self.scroll_to_selected = true;
+            } else if input.key_pressed(Key::ArrowUp) && index > 0 {
+                self.selected = self.rows[index - 1].id;
+                self.scroll_to_selected = true;
+            } else if (input.key_pressed(Key::ArrowRight) && row.has_children && !open)
+                || (input.key_pressed(Key::ArrowLeft) && open)
+            {
+                *toggled = Some(row.id);
+            }
+        });
+    }
+
+    fn rebuild_rows<'a>(&mut self, children: &impl Fn(u32) -> &'a [u32]) {
+        self.rows.clear();
+        self.dirty = false;
+
+        let mut stack = vec![(self.root, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let node_children = children(id);
+            self.rows.push(Row {
+                id,
+                depth,
+                has_children: !node_children.is_empty(),
+            });
+
+            if self.expanded.contains(&id) {
+                stack.extend(node_children.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use eframe::egui::*;
+
+const HEADER_HEIGHT: f32 = 16.0;
+const PADDING: f32 = 3.0;
+/// Items smaller than this are not split into their children.
+const MIN_NESTED_SIZE: f32 = 48.0;
+
+#[derive(Clone)]
+pub struct TreemapItem {
+    pub label: String,
+    pub value: f64,
+    pub color: Color32,
+    pub children: Vec<TreemapItem>,
+}
+
+pub struct TreemapResponse {
+    pub response: Response,
+    /// Indices from the top level down to the item under the mouse pointer.
+    pub hovered: Option<Vec<usize>>,
+    /// Indices of the item clicked on.
+    pub clicked: Option<Vec<usize>>,
+}
+
+/// Nested rectangles sized by value, laid out with the squarified algorithm so that the
+/// rectangles stay close to squares.
+pub struct Treemap<'a> {
+    items: &'a [TreemapItem],
+    format_value: &'a dyn Fn(f64) -> String,
+}
+
+impl<'a> Treemap<'a> {
+    pub fn new(items: &'a [TreemapItem], format_value: &'a dyn Fn(f64) -> String) -> Self {
+        Self {
+            items,
+            format_value,
+        }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> TreemapResponse {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::click());
+        let painter = ui.painter_at(rect);
+        let pointer = response.hover_pos();
+
+        let mut hovered = None;
+        let mut path = Vec::new();
+        self.draw_items(&painter, self.items, rect, pointer, &mut path, &mut hovered);
+
+        let clicked = if response.clicked() {
+            hovered.clone()
+        } else {
+            None
+        };
+
+        TreemapResponse {
+            response,
+            hovered,
+            clicked,
+        }
+    }
+
+    fn draw_items(
+        &self,
+        painter: &Painter,
+        items: &[TreemapItem],
+        rect: Rect,
+        pointer: Option<Pos2>,
+        path: &mut Vec<usize>,
+        hovered: &mut Option<Vec<usize>>,
+    ) {
+        let values = items.iter().map(|item| item.value).collect::<Vec<_>>();
+
+        for (idx, item_rect) in squarify(&values, rect) {
+            let item = &items[idx];
+            path.push(idx);
+
+            let is_hovered = pointer.is_some_and(|pos| item_rect.contains(pos));
+            if is_hovered {
+                *hovered = Some(path.clone());
+            }
+
+            let color = if is_hovered {
+                item.color.gamma_multiply(1.2)
+            } else {
+                item.color
+            };
+            painter.rect_filled(item_rect, 2.0, color);
+            painter.rect_stroke(
+                item_rect,
+                2.0,
+                Stroke::new(1.0, Color32::from_black_alpha(120)),
+                StrokeKind::Inside,
+            );
+
+            let nested = !item.children.is_empty()
+                && item_rect.width() > MIN_NESTED_SIZE
+                && item_rect.height() > MIN_NESTED_SIZE;
+
+            let text = format!("{}: {}", item.label, (self.format_value)(item.value));
+            let clipped = painter.with_clip_rect(item_rect.shrink(PADDING));
+            clipped.text(
+                item_rect.min + vec2(PADDING, PADDING),
+                Align2::LEFT_TOP,
+                text,
+                FontId::proportional(12.0),
+                Color32::BLACK,
+            );
+
+            if nested {
+                let mut inner = item_rect.shrink(PADDING);
+                inner.min.y += HEADER_HEIGHT;
+                self.draw_items(painter, &item.children, inner, pointer, path, hovered);
+            }
+
+            path.pop();
+        }
+    }
+}
+
+/// Rectangles of the items with positive values, larger items first.
+fn squarify(values: &[f64], rect: Rect) -> Vec<(usize, Rect)> {
+    let mut order = (0..values.len())
+        .filter(|idx| values[*idx] > 0.0)
+        .collect::<Vec<_>>();
+    order.sort_by(|a, b| values[*b].total_cmp(&values[*a]));
+
+    let total = order.iter().map(|idx| values[*idx]).sum::<f64>();
+    if total <= 0.0 || rect.area() <= 0.0 {
+        return Vec::new();
+    }
+
+    let scale = rect.area() as f64 / total;
+    let areas = order
+        .iter()
+        .map(|idx| (values[*idx] * scale) as f32)
+        .collect::<Vec<_>>();
+
+    let mut rects = Vec::with_capacity(order.len());
+    let mut free = rect;
+    let mut start = 0;
+
+    while start < areas.len() {
+        let side = free.width().min(free.height());
+
+        let mut end = start + 1;
+        let mut worst = worst_ratio(&areas[start..end], side);
+        while end < areas.len() {
+            let ratio = worst_ratio(&areas[start..end + 1], side);
+            if ratio > worst {
+                break;
+            }
+            worst = ratio;
+            end += 1;
+        }
+
+        let row_area = areas[start..end].iter().sum::<f32>();
+        let thickness = row_area / side;
+        let mut offset = 0.0;
+
+        for (i, area) in areas[start..end].iter().enumerate() {
+            let length = area / thickness;
+            let item_rect = if free.width() >= free.height() {
+                Rect::from_min_size(free.min + vec2(0.0, offset), vec2(thickness, length))
+            } else {
+                Rect::from_min_size(free.min + vec2(offset, 0.0), vec2(length, thickness))
+            };
+            rects.push((order[start + i], item_rect));
+            offset += length;
+        }
+
+        if free.width() >= free.height() {
+            free.min.x += thickness;
+        } else {
+            free.min.y += thickness;
+        }
+        start = end;
+    }
+
+    rects
+}
+
+/// Worst aspect ratio of a row of areas laid along a side of the given length.
+fn worst_ratio(row: &[f32], side: f32) -> f32 {
+    let sum = row.iter().sum::<f32>();
+    let (min, max) = row.iter().fold((f32::MAX, 0.0f32), |(min, max), a| {
+        (min.min(*a), max.max(*a))
+    });
+    let side2 = side * side;
+    let sum2 = sum * sum;
+
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}